@@ -0,0 +1,131 @@
+use image::RgbaImage;
+
+// Layout of the frames in a flipbook atlas
+#[derive(Debug, PartialEq)]
+pub struct AtlasLayout {
+    pub columns: u32,
+    pub rows: u32,
+    pub frame_count: u32,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub padding: u32
+}
+
+impl AtlasLayout {
+    // Lay frames out in a grid, as square as possible unless the column count is given
+    pub fn new(
+        frame_count: u32, frame_width: u32, frame_height: u32, columns: Option<u32>, padding: u32
+    ) -> Result<AtlasLayout, String> {
+        let columns = match columns {
+            Some(0) => return Err("Column count must be greater than 0".into()),
+            Some(columns) => columns.min(frame_count),
+            None => (frame_count as f64).sqrt().ceil() as u32
+        };
+
+        Ok(AtlasLayout {
+            columns,
+            rows: frame_count.div_ceil(columns),
+            frame_count,
+            frame_width,
+            frame_height,
+            padding
+        })
+    }
+
+    // Get the size of a frame's cell, including its padding on both sides
+    pub fn cell_size(&self) -> (u32, u32) {
+        (
+            self.frame_width + 2 * self.padding,
+            self.frame_height + 2 * self.padding
+        )
+    }
+
+    // Get the size of the whole atlas
+    pub fn atlas_size(&self) -> (u32, u32) {
+        let (cell_width, cell_height) = self.cell_size();
+        (self.columns * cell_width, self.rows * cell_height)
+    }
+
+    // Get the largest frame size, keeping its aspect ratio, that keeps the atlas within the given size
+    // Returns None if not even a single pixel per frame fits
+    pub fn fit_frame_size(&self, max_size: u32) -> Option<(u32, u32)> {
+        let (atlas_width, atlas_height) = self.atlas_size();
+
+        if atlas_width <= max_size && atlas_height <= max_size {
+            return Some((self.frame_width, self.frame_height));
+        }
+
+        let max_frame_width = (max_size / self.columns).checked_sub(2 * self.padding)?;
+        let max_frame_height = (max_size / self.rows).checked_sub(2 * self.padding)?;
+
+        let scale = f64::min(
+            max_frame_width as f64 / self.frame_width as f64,
+            max_frame_height as f64 / self.frame_height as f64
+        );
+
+        let frame_width = (self.frame_width as f64 * scale).floor() as u32;
+        let frame_height = (self.frame_height as f64 * scale).floor() as u32;
+
+        match frame_width > 0 && frame_height > 0 {
+            true => Some((frame_width, frame_height)),
+            false => None
+        }
+    }
+
+    // Describe the layout as JSON, for the sidecar file
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\n  \"columns\": {},\n  \"rows\": {},\n  \"frames\": {},\n  \"frame_width\": {},\n  \
+             \"frame_height\": {},\n  \"padding\": {}\n}}\n",
+            self.columns, self.rows, self.frame_count, self.frame_width, self.frame_height, self.padding
+        )
+    }
+}
+
+// Pack frames into a grid atlas, left to right and top to bottom
+// Every frame is surrounded by copies of its edge pixels, so filtering doesn't bleed into the next frame
+pub fn build_atlas(
+    frames: &[RgbaImage], columns: Option<u32>, padding: u32
+) -> Result<(RgbaImage, AtlasLayout), String> {
+    if frames.is_empty() {
+        return Err("There are no frames to pack".into());
+    }
+
+    // Frames must all be the same size
+    let (frame_width, frame_height) = frames[0].dimensions();
+
+    for (i, frame) in frames.iter().enumerate() {
+        if frame.dimensions() != (frame_width, frame_height) {
+            return Err(format!(
+                "Frame {} is {}x{}, expected {}x{}",
+                i,
+                frame.width(),
+                frame.height(),
+                frame_width,
+                frame_height
+            ));
+        }
+    }
+
+    let layout = AtlasLayout::new(frames.len() as u32, frame_width, frame_height, columns, padding)?;
+    let (atlas_width, atlas_height) = layout.atlas_size();
+    let (cell_width, cell_height) = layout.cell_size();
+
+    // Unused cells are left transparent
+    let mut atlas = RgbaImage::new(atlas_width, atlas_height);
+
+    for (i, frame) in frames.iter().enumerate() {
+        let cell_x = i as u32 % layout.columns * cell_width;
+        let cell_y = i as u32 / layout.columns * cell_height;
+
+        for y in 0..cell_height {
+            for x in 0..cell_width {
+                let frame_x = x.saturating_sub(padding).min(frame_width - 1);
+                let frame_y = y.saturating_sub(padding).min(frame_height - 1);
+                atlas.put_pixel(cell_x + x, cell_y + y, *frame.get_pixel(frame_x, frame_y));
+            }
+        }
+    }
+
+    Ok((atlas, layout))
+}
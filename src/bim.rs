@@ -1,5 +1,7 @@
 use texpresso::Format;
 
+use crate::utils;
+
 // Texture material kind for bimage enum
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[allow(dead_code)]
@@ -45,6 +47,7 @@ impl TextureMaterialKind {
             _ if file_name.contains("$mtlkind=heightmap") => TextureMaterialKind::TmkHeightmap,
             _ if file_name.contains("$mtlkind=font") => TextureMaterialKind::TmkFont,
             _ if file_name.contains("$mtlkind=bloommask") => TextureMaterialKind::TmkBloommask,
+            _ if utils::is_font_file(&file_name) => TextureMaterialKind::TmkFont,
             _ if stripped_file_name.ends_with("_n") => TextureMaterialKind::TmkNormal,
             _ if stripped_file_name.ends_with("_Normal") => TextureMaterialKind::TmkNormal,
             _ if stripped_file_name.ends_with("_s") => TextureMaterialKind::TmkSpecular,
@@ -58,8 +61,17 @@ impl TextureMaterialKind {
     }
 }
 
+// Texture types for bimage
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[allow(dead_code)]
+pub enum TextureType {
+    Tt2d    = 0x0,
+    Tt3d    = 0x1,
+    TtCubic = 0x2
+}
+
 // DDS texture formats used by bimage
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 #[allow(dead_code)]
 pub enum TextureFormat {
     FmtNone           = 0x0,
@@ -121,19 +133,110 @@ pub enum TextureFormat {
 }
 
 impl TextureFormat {
-    // Get block size for format
-    pub fn block_size(&self) -> Option<u32> {
+    // Get texture format from its value
+    pub fn from_u32(value: u32) -> Option<TextureFormat> {
+        match value {
+            0x0 => Some(TextureFormat::FmtNone),
+            0x1 => Some(TextureFormat::FmtRgba32f),
+            0x2 => Some(TextureFormat::FmtRgba16f),
+            0x3 => Some(TextureFormat::FmtRgba8),
+            0x4 => Some(TextureFormat::FmtArgb8),
+            0x5 => Some(TextureFormat::FmtAlpha),
+            0x6 => Some(TextureFormat::FmtL8a8Deprecated),
+            0x7 => Some(TextureFormat::FmtRg8),
+            0x8 => Some(TextureFormat::FmtLum8Deprecated),
+            0x9 => Some(TextureFormat::FmtInt8Deprecated),
+            0xA => Some(TextureFormat::FmtBc1),
+            0xB => Some(TextureFormat::FmtBc3),
+            0xC => Some(TextureFormat::FmtDepth),
+            0xD => Some(TextureFormat::FmtDepthStencil),
+            0xE => Some(TextureFormat::FmtX32f),
+            0xF => Some(TextureFormat::FmtY16fX16f),
+            0x10 => Some(TextureFormat::FmtX16),
+            0x11 => Some(TextureFormat::FmtY16X16),
+            0x12 => Some(TextureFormat::FmtRgb565),
+            0x13 => Some(TextureFormat::FmtR8),
+            0x14 => Some(TextureFormat::FmtR11fg11fb10f),
+            0x15 => Some(TextureFormat::FmtX16f),
+            0x16 => Some(TextureFormat::FmtBc6hUf16),
+            0x17 => Some(TextureFormat::FmtBc7),
+            0x18 => Some(TextureFormat::FmtBc4),
+            0x19 => Some(TextureFormat::FmtBc5),
+            0x1A => Some(TextureFormat::FmtRg16f),
+            0x1B => Some(TextureFormat::FmtR10g10b10a2),
+            0x1C => Some(TextureFormat::FmtRg32f),
+            0x1D => Some(TextureFormat::FmtR32Uint),
+            0x1E => Some(TextureFormat::FmtR16Uint),
+            0x1F => Some(TextureFormat::FmtDepth16),
+            0x20 => Some(TextureFormat::FmtRgba8Srgb),
+            0x21 => Some(TextureFormat::FmtBc1Srgb),
+            0x22 => Some(TextureFormat::FmtBc3Srgb),
+            0x23 => Some(TextureFormat::FmtBc7Srgb),
+            0x24 => Some(TextureFormat::FmtBc6hSf16),
+            0x25 => Some(TextureFormat::FmtAstc4x4),
+            0x26 => Some(TextureFormat::FmtAstc4x4Srgb),
+            0x27 => Some(TextureFormat::FmtAstc5x4),
+            0x28 => Some(TextureFormat::FmtAstc5x4Srgb),
+            0x29 => Some(TextureFormat::FmtAstc5x5),
+            0x2A => Some(TextureFormat::FmtAstc5x5Srgb),
+            0x2B => Some(TextureFormat::FmtAstc6x5),
+            0x2C => Some(TextureFormat::FmtAstc6x5Srgb),
+            0x2D => Some(TextureFormat::FmtAstc6x6),
+            0x2E => Some(TextureFormat::FmtAstc6x6Srgb),
+            0x2F => Some(TextureFormat::FmtAstc8x5),
+            0x30 => Some(TextureFormat::FmtAstc8x5Srgb),
+            0x31 => Some(TextureFormat::FmtAstc8x6),
+            0x32 => Some(TextureFormat::FmtAstc8x6Srgb),
+            0x33 => Some(TextureFormat::FmtAstc8x8),
+            0x34 => Some(TextureFormat::FmtAstc8x8Srgb),
+            0x35 => Some(TextureFormat::FmtDepth32f),
+            0x36 => Some(TextureFormat::FmtBc1ZeroAlpha),
+            0x37 => Some(TextureFormat::FmtNextAvailable),
+            _ => None
+        }
+    }
+
+    // Get the size in bytes of a mip with the given dimensions
+    pub fn mip_size(&self, width: u32, height: u32) -> Option<usize> {
+        let (width, height) = (width as usize, height as usize);
+        let block_count = width.div_ceil(4) * height.div_ceil(4);
+
         match self {
-            TextureFormat::FmtAlpha => Some(8),
-            TextureFormat::FmtBc1Srgb => Some(8),
-            TextureFormat::FmtBc3 => Some(16),
-            TextureFormat::FmtBc4 => Some(8),
-            TextureFormat::FmtBc5 => Some(16),
-            TextureFormat::FmtBc7 => Some(16),
+            TextureFormat::FmtAlpha | TextureFormat::FmtR8 => Some(width * height),
+            TextureFormat::FmtRgba8 | TextureFormat::FmtRgba8Srgb => Some(width * height * 4),
+            TextureFormat::FmtBc1 | TextureFormat::FmtBc1Srgb | TextureFormat::FmtBc4 => {
+                Some(block_count * 8)
+            },
+            TextureFormat::FmtBc3
+            | TextureFormat::FmtBc3Srgb
+            | TextureFormat::FmtBc5
+            | TextureFormat::FmtBc6hUf16
+            | TextureFormat::FmtBc6hSf16
+            | TextureFormat::FmtBc7
+            | TextureFormat::FmtBc7Srgb => Some(block_count * 16),
             _ => None
         }
     }
 
+    // Check if the format stores colors in sRGB
+    pub fn is_srgb(&self) -> bool {
+        matches!(
+            self,
+            TextureFormat::FmtRgba8Srgb
+                | TextureFormat::FmtBc1Srgb
+                | TextureFormat::FmtBc3Srgb
+                | TextureFormat::FmtBc7Srgb
+                | TextureFormat::FmtAstc4x4Srgb
+                | TextureFormat::FmtAstc5x4Srgb
+                | TextureFormat::FmtAstc5x5Srgb
+                | TextureFormat::FmtAstc6x5Srgb
+                | TextureFormat::FmtAstc6x6Srgb
+                | TextureFormat::FmtAstc8x5Srgb
+                | TextureFormat::FmtAstc8x6Srgb
+                | TextureFormat::FmtAstc8x8Srgb
+        )
+    }
+
     // Convert to texpresso format for compression
     pub fn as_texpresso_format(&self) -> Result<Format, String> {
         match self {
@@ -146,6 +249,10 @@ impl TextureFormat {
     }
 }
 
+// Size of BIMHeader and BIMMipMap in bytes
+pub const BIM_HEADER_SIZE: usize = 63;
+pub const BIM_MIP_MAP_SIZE: usize = 36;
+
 // BIM header
 pub struct BIMHeader {
     pub signature: [u8; 3],
@@ -192,15 +299,48 @@ impl Default for BIMHeader {
             unk_bool: 0,
             bool_no_mips: 0,
             bool_fft_bloom: 0,
+            // Every mip is stored in the bimage itself
             streamdb_mip_count: 0
         }
     }
 }
 
 impl BIMHeader {
+    // Parse BIMHeader from its bytes representation, returns None if the signature doesn't match
+    pub fn from_bytes(bytes: &[u8]) -> Option<BIMHeader> {
+        if bytes.len() < BIM_HEADER_SIZE || bytes[0..3] != [0x42, 0x49, 0x4D] {
+            return None;
+        }
+
+        let read_u32 = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        Some(BIMHeader {
+            signature: [bytes[0], bytes[1], bytes[2]],
+            version: bytes[3],
+            texture_type: read_u32(4),
+            texture_material_kind: read_u32(8),
+            pixel_width: read_u32(12),
+            pixel_height: read_u32(16),
+            depth: read_u32(20),
+            mip_count: read_u32(24),
+            mip_level: i64::from_le_bytes(bytes[28..36].try_into().unwrap()),
+            unk_float_1: f32::from_le_bytes(bytes[36..40].try_into().unwrap()),
+            bool_is_environment_map: bytes[40],
+            texture_format: read_u32(41),
+            always_7: read_u32(45),
+            null_padding: read_u32(49),
+            atlas_padding: i16::from_le_bytes(bytes[53..55].try_into().unwrap()),
+            bool_is_streamed: bytes[55],
+            unk_bool: bytes[56],
+            bool_no_mips: bytes[57],
+            bool_fft_bloom: bytes[58],
+            streamdb_mip_count: read_u32(59)
+        })
+    }
+
     // Convert BIMHeader to bytes representation
-    pub fn to_bytes(&self) -> [u8; 63] {
-        let mut bytes = [0_u8; 63];
+    pub fn to_bytes(&self) -> [u8; BIM_HEADER_SIZE] {
+        let mut bytes = [0_u8; BIM_HEADER_SIZE];
 
         bytes[0..3].copy_from_slice(&self.signature);
         bytes[3] = self.version;
@@ -229,7 +369,9 @@ impl BIMHeader {
 
 // BIM mipmap
 pub struct BIMMipMap {
-    pub mip_level: i64,
+    pub mip_level: i32,
+    // Cubemap face or volume slice the mip belongs to
+    pub mip_dest_z: i32,
     pub mip_pixel_width: u32,
     pub mip_pixel_height: u32,
     pub unknown_flag: u32,
@@ -243,6 +385,7 @@ impl Default for BIMMipMap {
     fn default() -> Self {
         Self {
             mip_level: 0,
+            mip_dest_z: 0,
             mip_pixel_width: 0,
             mip_pixel_height: 0,
             unknown_flag: 1,
@@ -255,11 +398,29 @@ impl Default for BIMMipMap {
 }
 
 impl BIMMipMap {
+    // Parse BIMMipMap from its bytes representation
+    pub fn from_bytes(bytes: &[u8]) -> BIMMipMap {
+        let read_u32 = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        BIMMipMap {
+            mip_level: read_u32(0) as i32,
+            mip_dest_z: read_u32(4) as i32,
+            mip_pixel_width: read_u32(8),
+            mip_pixel_height: read_u32(12),
+            unknown_flag: read_u32(16),
+            decompressed_size: read_u32(20),
+            flag_is_compressed: read_u32(24),
+            compressed_size: read_u32(28),
+            cumulative_size_streamdb: read_u32(32)
+        }
+    }
+
     // Convert BIMMipMap to bytes representation
-    pub fn to_bytes(&self) -> [u8; 36] {
-        let mut bytes = [0_u8; 36];
+    pub fn to_bytes(&self) -> [u8; BIM_MIP_MAP_SIZE] {
+        let mut bytes = [0_u8; BIM_MIP_MAP_SIZE];
 
-        bytes[0..8].copy_from_slice(&self.mip_level.to_le_bytes());
+        bytes[0..4].copy_from_slice(&self.mip_level.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.mip_dest_z.to_le_bytes());
         bytes[8..12].copy_from_slice(&self.mip_pixel_width.to_le_bytes());
         bytes[12..16].copy_from_slice(&self.mip_pixel_height.to_le_bytes());
         bytes[16..20].copy_from_slice(&self.unknown_flag.to_le_bytes());
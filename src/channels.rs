@@ -0,0 +1,238 @@
+use std::{
+    array,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr
+};
+
+use image::{Rgba, RgbaImage};
+
+// Channel of a source image
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    Luminance
+}
+
+impl FromStr for Channel {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "r" | "red" => Ok(Channel::Red),
+            "g" | "green" => Ok(Channel::Green),
+            "b" | "blue" => Ok(Channel::Blue),
+            "a" | "alpha" => Ok(Channel::Alpha),
+            "l" | "luminance" => Ok(Channel::Luminance),
+            _ => Err(())
+        }
+    }
+}
+
+impl Channel {
+    // Get the channel's name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Red => "red",
+            Channel::Green => "green",
+            Channel::Blue => "blue",
+            Channel::Alpha => "alpha",
+            Channel::Luminance => "luminance"
+        }
+    }
+
+    // Pick alpha for images with any transparency, and luminance for opaque ones
+    pub fn detect(img: &RgbaImage) -> Channel {
+        match img.pixels().all(|pixel| pixel[3] == 255) {
+            true => Channel::Luminance,
+            false => Channel::Alpha
+        }
+    }
+
+    // Read the channel's value from a pixel
+    pub fn read(&self, pixel: &Rgba<u8>) -> u8 {
+        let [r, g, b, a] = pixel.0;
+
+        match self {
+            Channel::Red => r,
+            Channel::Green => g,
+            Channel::Blue => b,
+            Channel::Alpha => a,
+            Channel::Luminance => (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32)
+                .round()
+                .min(255.0) as u8
+        }
+    }
+}
+
+// Copy the given channel into the color channels, and into alpha too unless it should be left opaque
+pub fn fill_from_channel(img: &mut RgbaImage, channel: Channel, opaque: bool) {
+    for pixel in img.pixels_mut() {
+        let value = channel.read(pixel);
+
+        *pixel = match opaque {
+            true => Rgba([value, value, value, 255]),
+            false => Rgba([value; 4])
+        };
+    }
+}
+
+// Where a packed texture's channel comes from
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelSource {
+    Constant(u8),
+    Image {
+        path: PathBuf,
+        channel: Channel,
+        invert: bool
+    }
+}
+
+impl ChannelSource {
+    // Parse a channel source like '128', 'ao.png', 'mask.png:g' or 'gloss.png:r invert'
+    // Image paths are relative to the given directory
+    pub fn parse(text: &str, base_dir: &Path) -> Result<ChannelSource, String> {
+        let (text, invert) = match text.strip_suffix(" invert") {
+            Some(text) => (text.trim_end(), true),
+            None => (text, false)
+        };
+
+        if let Ok(value) = text.parse::<u8>() {
+            return Ok(ChannelSource::Constant(match invert {
+                true => 255 - value,
+                false => value
+            }));
+        }
+
+        // Images are read from luminance unless a channel is given
+        let (path, channel) = match text.rsplit_once(':') {
+            Some((path, channel)) if channel.parse::<Channel>().is_ok() => (path, channel.parse().unwrap()),
+            _ => (text, Channel::Luminance)
+        };
+
+        if path.is_empty() {
+            return Err(format!("Invalid channel source '{}'", text));
+        }
+
+        Ok(ChannelSource::Image {
+            path: base_dir.join(path),
+            channel,
+            invert
+        })
+    }
+
+    // Read the source's value at the given pixel of the loaded images
+    pub fn read(&self, images: &HashMap<&Path, RgbaImage>, x: u32, y: u32) -> u8 {
+        match self {
+            ChannelSource::Constant(value) => *value,
+            ChannelSource::Image {
+                path,
+                channel,
+                invert
+            } => {
+                let value = channel.read(images[path.as_path()].get_pixel(x, y));
+
+                match invert {
+                    true => 255 - value,
+                    false => value
+                }
+            }
+        }
+    }
+}
+
+// Recipe to assemble a texture from the channels of several images
+#[derive(Debug, PartialEq)]
+pub struct PackRecipe {
+    pub sources: [ChannelSource; 4]
+}
+
+impl PackRecipe {
+    // Parse a recipe with one 'channel = source' line per channel, e.g. 'g = rough.png:r invert'
+    // Missing color channels are black and missing alpha is opaque
+    pub fn parse(text: &str, base_dir: &Path) -> Result<PackRecipe, String> {
+        let mut sources = [
+            ChannelSource::Constant(0),
+            ChannelSource::Constant(0),
+            ChannelSource::Constant(0),
+            ChannelSource::Constant(255)
+        ];
+
+        for line in text.lines() {
+            // Skip comments and empty lines
+            let line = line.split('#').next().unwrap().trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let (target, source) = line
+                .split_once('=')
+                .ok_or(format!("Expected 'channel = source', found '{}'", line))?;
+
+            let index = match target.trim() {
+                "r" | "red" => 0,
+                "g" | "green" => 1,
+                "b" | "blue" => 2,
+                "a" | "alpha" => 3,
+                target => return Err(format!("Invalid target channel '{}'", target))
+            };
+
+            sources[index] = ChannelSource::parse(source.trim(), base_dir)?;
+        }
+
+        Ok(PackRecipe { sources })
+    }
+
+    // Get the paths of every image used by the recipe, without duplicates
+    pub fn image_paths(&self) -> Vec<&Path> {
+        let mut paths: Vec<&Path> = Vec::new();
+
+        for source in &self.sources {
+            if let ChannelSource::Image { path, .. } = source {
+                if !paths.contains(&path.as_path()) {
+                    paths.push(path);
+                }
+            }
+        }
+
+        paths
+    }
+
+    // Assemble the texture from the loaded images, which must all be the same size
+    pub fn pack(&self, images: &HashMap<&Path, RgbaImage>) -> Result<RgbaImage, String> {
+        let (width, height) = common_size(&self.image_paths(), images)?;
+
+        Ok(RgbaImage::from_fn(width, height, |x, y| {
+            Rgba(array::from_fn(|i| self.sources[i].read(images, x, y)))
+        }))
+    }
+}
+
+// Get the size shared by the loaded images at the given paths
+pub fn common_size(paths: &[&Path], images: &HashMap<&Path, RgbaImage>) -> Result<(u32, u32), String> {
+    let (width, height) = match paths.first() {
+        Some(path) => images[path].dimensions(),
+        None => return Err("The recipe doesn't use any image".into())
+    };
+
+    for path in paths {
+        let img = &images[path];
+
+        if img.dimensions() != (width, height) {
+            return Err(format!(
+                "'{}' is {}x{}, expected {}x{}",
+                path.display(),
+                img.width(),
+                img.height(),
+                width,
+                height
+            ));
+        }
+    }
+
+    Ok((width, height))
+}
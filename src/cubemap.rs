@@ -0,0 +1,404 @@
+use std::{f32::consts::PI, str::FromStr, thread};
+
+use image::{Rgba, RgbaImage};
+
+use crate::utils::{linear_to_srgb, srgb_to_linear, to_unorm8};
+
+// Filter used to sample panoramas
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SampleFilter {
+    Bilinear,
+    Bicubic
+}
+
+impl FromStr for SampleFilter {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "bilinear" => Ok(SampleFilter::Bilinear),
+            "bicubic" => Ok(SampleFilter::Bicubic),
+            _ => Err(())
+        }
+    }
+}
+
+impl SampleFilter {
+    // Get the offset of the first texel and the weights of every texel to blend, for the given fraction
+    fn weights(&self, t: f32) -> (i64, Vec<f32>) {
+        match self {
+            SampleFilter::Bilinear => (0, vec![1.0 - t, t]),
+            // Catmull-Rom spline
+            SampleFilter::Bicubic => {
+                let (t2, t3) = (t * t, t * t * t);
+
+                (
+                    -1,
+                    vec![
+                        (-t3 + 2.0 * t2 - t) / 2.0,
+                        (3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+                        (-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+                        (t3 - t2) / 2.0,
+                    ]
+                )
+            }
+        }
+    }
+}
+
+// Get the normalized direction of the given point of a cube face, with faces ordered +X, -X, +Y, -Y, +Z, -Z
+// s and t go from -1 to 1, left to right and top to bottom
+pub fn face_direction(face: u32, s: f32, t: f32) -> [f32; 3] {
+    let [x, y, z] = match face {
+        0 => [1.0, -t, -s],
+        1 => [-1.0, -t, s],
+        2 => [s, 1.0, t],
+        3 => [s, -1.0, -t],
+        4 => [s, -t, 1.0],
+        _ => [-s, -t, -1.0]
+    };
+
+    let length = (x * x + y * y + z * z).sqrt();
+    [x / length, y / length, z / length]
+}
+
+// Get the direction through the center of the given texel of a cube face
+pub fn texel_direction(face: u32, x: u32, y: u32, face_size: u32) -> [f32; 3] {
+    let s = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+    let t = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+    face_direction(face, s, t)
+}
+
+// Sample an equirectangular panorama in the given direction
+// The panorama wraps around horizontally, so there is no seam behind the viewer
+fn sample_equirect(img: &RgbaImage, direction: [f32; 3], filter: SampleFilter) -> Rgba<u8> {
+    let (width, height) = (img.width() as i64, img.height() as i64);
+
+    // +Z is at the center of the panorama, +X to its right and +Y at the top
+    let u = 0.5 + direction[0].atan2(direction[2]) / (2.0 * PI);
+    let v = 0.5 - direction[1].clamp(-1.0, 1.0).asin() / PI;
+
+    // Texel coordinates, with texel centers at .5
+    let x = u * width as f32 - 0.5;
+    let y = v * height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+
+    let (x_offset, x_weights) = filter.weights(x - x0);
+    let (y_offset, y_weights) = filter.weights(y - y0);
+
+    let mut color = [0.0_f32; 4];
+
+    for (j, y_weight) in y_weights.iter().enumerate() {
+        let texel_y = (y0 as i64 + y_offset + j as i64).clamp(0, height - 1);
+
+        for (i, x_weight) in x_weights.iter().enumerate() {
+            let texel_x = (x0 as i64 + x_offset + i as i64).rem_euclid(width);
+            let texel = img.get_pixel(texel_x as u32, texel_y as u32);
+
+            for (channel, value) in color.iter_mut().enumerate() {
+                *value += texel[channel] as f32 * x_weight * y_weight;
+            }
+        }
+    }
+
+    Rgba(color.map(|value| value.round().clamp(0.0, 255.0) as u8))
+}
+
+// Resample an equirectangular panorama into the six faces of a cubemap, ordered +X, -X, +Y, -Y, +Z, -Z
+pub fn equirect_to_cube_faces(img: &RgbaImage, face_size: u32, filter: SampleFilter) -> Vec<RgbaImage> {
+    thread::scope(|s| {
+        // Resample every face
+        let handles: Vec<_> = (0..6)
+            .map(|face| {
+                s.spawn(move || {
+                    RgbaImage::from_fn(face_size, face_size, |x, y| {
+                        sample_equirect(img, texel_direction(face, x, y, face_size), filter)
+                    })
+                })
+            })
+            .collect();
+
+        // Join all threads
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+// Get the face pointed at by the given direction, and the point in it (s and t from -1 to 1)
+fn direction_to_face(direction: [f32; 3]) -> (usize, f32, f32) {
+    let [x, y, z] = direction;
+    let (abs_x, abs_y, abs_z) = (x.abs(), y.abs(), z.abs());
+
+    match () {
+        _ if abs_x >= abs_y && abs_x >= abs_z && x > 0.0 => (0, -z / abs_x, -y / abs_x),
+        _ if abs_x >= abs_y && abs_x >= abs_z => (1, z / abs_x, -y / abs_x),
+        _ if abs_y >= abs_z && y > 0.0 => (2, x / abs_y, z / abs_y),
+        _ if abs_y >= abs_z => (3, x / abs_y, -z / abs_y),
+        _ if z > 0.0 => (4, x / abs_z, -y / abs_z),
+        _ => (5, -x / abs_z, -y / abs_z)
+    }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0]
+    ]
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let length = dot(a, a).sqrt();
+    a.map(|value| value / length)
+}
+
+// Every face of a cubemap mip, in floating point RGBA
+pub struct CubeLevel {
+    size: u32,
+    faces: Vec<Vec<[f32; 4]>>
+}
+
+impl CubeLevel {
+    // Convert faces to floating point, with colors in linear space
+    pub fn from_faces(faces: &[RgbaImage], srgb: bool) -> CubeLevel {
+        let texels = faces
+            .iter()
+            .map(|face| {
+                face.pixels()
+                    .map(|pixel| {
+                        let [r, g, b, a] = pixel.0;
+
+                        match srgb {
+                            true => [
+                                srgb_to_linear(r),
+                                srgb_to_linear(g),
+                                srgb_to_linear(b),
+                                a as f32 / 255.0
+                            ],
+                            false => [r, g, b, a].map(|value| value as f32 / 255.0)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        CubeLevel {
+            size: faces[0].width(),
+            faces: texels
+        }
+    }
+
+    // Halve the level's size by averaging every 2x2 block
+    fn downsample(&self) -> CubeLevel {
+        let size = (self.size / 2).max(1);
+        let last = self.size - 1;
+
+        let faces = self
+            .faces
+            .iter()
+            .map(|texels| {
+                (0..size * size)
+                    .map(|i| {
+                        let (x, y) = (i % size * 2, i / size * 2);
+                        let mut color = [0.0; 4];
+
+                        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                            let texel =
+                                texels[((y + dy).min(last) * self.size + (x + dx).min(last)) as usize];
+
+                            for (value, texel_value) in color.iter_mut().zip(texel) {
+                                *value += texel_value / 4.0;
+                            }
+                        }
+
+                        color
+                    })
+                    .collect()
+            })
+            .collect();
+
+        CubeLevel { size, faces }
+    }
+
+    // Get a texel of the given face, or the nearest texel of the adjacent face for coordinates past its edges
+    fn texel(&self, face: usize, x: i64, y: i64) -> [f32; 4] {
+        let size = self.size as i64;
+
+        if (0..size).contains(&x) && (0..size).contains(&y) {
+            return self.faces[face][(y * size + x) as usize];
+        }
+
+        // Follow the direction through the texel's center onto the face it lands on
+        let s = 2.0 * (x as f32 + 0.5) / self.size as f32 - 1.0;
+        let t = 2.0 * (y as f32 + 0.5) / self.size as f32 - 1.0;
+        let (face, s, t) = direction_to_face(face_direction(face as u32, s, t));
+
+        let x = (((s + 1.0) / 2.0 * self.size as f32) as i64).clamp(0, size - 1);
+        let y = (((t + 1.0) / 2.0 * self.size as f32) as i64).clamp(0, size - 1);
+        self.faces[face][(y * size + x) as usize]
+    }
+
+    // Bilinearly sample the level in the given direction, continuing onto the adjacent faces across edges
+    pub fn sample(&self, direction: [f32; 3]) -> [f32; 4] {
+        let (face, s, t) = direction_to_face(direction);
+        let size = self.size as f32;
+
+        let x = (s + 1.0) / 2.0 * size - 0.5;
+        let y = (t + 1.0) / 2.0 * size - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let (top_left, top_right, bottom_left, bottom_right) = (
+            self.texel(face, x0, y0),
+            self.texel(face, x0 + 1, y0),
+            self.texel(face, x0, y0 + 1),
+            self.texel(face, x0 + 1, y0 + 1)
+        );
+
+        let mut color = [0.0; 4];
+
+        for (channel, value) in color.iter_mut().enumerate() {
+            let top = top_left[channel] * (1.0 - fx) + top_right[channel] * fx;
+            let bottom = bottom_left[channel] * (1.0 - fx) + bottom_right[channel] * fx;
+            *value = top * (1.0 - fy) + bottom * fy;
+        }
+
+        color
+    }
+}
+
+// Get a sequence point, evenly distributed for any sample count
+fn hammersley(i: u32, sample_count: u32) -> (f32, f32) {
+    (
+        i as f32 / sample_count as f32,
+        i.reverse_bits() as f32 / 4294967296.0
+    )
+}
+
+// Convolve the cubemap with the GGX distribution around the given normal, which is also the view direction
+fn prefilter_texel(
+    sources: &[CubeLevel], normal: [f32; 3], alpha: f32, sample_count: u32, texel_solid_angle: f32
+) -> [f32; 4] {
+    // Tangent space around the normal
+    let up = match normal[2].abs() < 0.999 {
+        true => [0.0, 0.0, 1.0],
+        false => [1.0, 0.0, 0.0]
+    };
+
+    let tangent = normalize(cross(up, normal));
+    let bitangent = cross(normal, tangent);
+    let alpha2 = alpha * alpha;
+
+    let mut color = [0.0; 4];
+    let mut total_weight = 0.0;
+
+    for i in 0..sample_count {
+        // Importance sample the half vector
+        let (xi1, xi2) = hammersley(i, sample_count);
+        let phi = 2.0 * PI * xi1;
+        let cos_theta = ((1.0 - xi2) / (1.0 + (alpha2 - 1.0) * xi2)).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let half: [f32; 3] = std::array::from_fn(|axis| {
+            tangent[axis] * sin_theta * phi.cos()
+                + bitangent[axis] * sin_theta * phi.sin()
+                + normal[axis] * cos_theta
+        });
+
+        // Reflect the view direction around the half vector
+        let light: [f32; 3] = std::array::from_fn(|axis| 2.0 * cos_theta * half[axis] - normal[axis]);
+        let n_dot_l = dot(normal, light);
+
+        if n_dot_l <= 0.0 {
+            continue;
+        }
+
+        // Sample the source level whose texels cover about as much as the sample does, to avoid aliasing
+        let distribution = alpha2 / (PI * (cos_theta * cos_theta * (alpha2 - 1.0) + 1.0).powi(2));
+        let sample_solid_angle = 1.0 / (sample_count as f32 * distribution / 4.0 + 0.0001);
+        let level = (0.5 * (sample_solid_angle / texel_solid_angle).log2() + 1.0)
+            .round()
+            .clamp(0.0, (sources.len() - 1) as f32);
+
+        let sample = sources[level as usize].sample(light);
+
+        for (value, sample_value) in color.iter_mut().zip(sample) {
+            *value += sample_value * n_dot_l;
+        }
+
+        total_weight += n_dot_l;
+    }
+
+    color.map(|value| value / total_weight)
+}
+
+// Generate the mips of a reflection cubemap, each one convolved with the GGX distribution at increasing roughness
+// Mip 0 is the mirror-like source, and the last mip has a roughness of 1
+// Returns the RGBA bytes of every mip of every face
+pub fn prefilter_cube_faces(
+    faces: &[RgbaImage], mip_count: u32, sample_count: u32, srgb: bool
+) -> Vec<Vec<Vec<u8>>> {
+    let size = faces[0].width();
+
+    // Box filtered source levels, to sample wide lobes from
+    let mut sources = vec![CubeLevel::from_faces(faces, srgb)];
+
+    while sources.last().unwrap().size > 1 {
+        let source = sources.last().unwrap().downsample();
+        sources.push(source);
+    }
+
+    let texel_solid_angle = 4.0 * PI / (6 * size * size) as f32;
+
+    thread::scope(|s| {
+        // Prefilter every face
+        let handles: Vec<_> = (0..6)
+            .map(|face| {
+                let sources = &sources;
+
+                s.spawn(move || {
+                    (0..mip_count)
+                        .map(|level| {
+                            if level == 0 {
+                                return faces[face as usize].as_raw().clone();
+                            }
+
+                            let level_size = (size >> level).max(1);
+                            let roughness = level as f32 / (mip_count - 1) as f32;
+                            let mut bytes = Vec::with_capacity((level_size * level_size * 4) as usize);
+
+                            for y in 0..level_size {
+                                for x in 0..level_size {
+                                    let normal = texel_direction(face, x, y, level_size);
+                                    let [r, g, b, a] = prefilter_texel(
+                                        sources,
+                                        normal,
+                                        roughness * roughness,
+                                        sample_count,
+                                        texel_solid_angle
+                                    );
+
+                                    let [r, g, b] = match srgb {
+                                        true => [r, g, b].map(linear_to_srgb),
+                                        false => [r, g, b].map(to_unorm8)
+                                    };
+
+                                    bytes.extend([r, g, b, to_unorm8(a)]);
+                                }
+                            }
+
+                            bytes
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+
+        // Join all threads
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    })
+}
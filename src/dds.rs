@@ -0,0 +1,319 @@
+use std::{fs, path::Path};
+
+use crate::bim::{TextureFormat, TextureType};
+
+// DDS magic, "DDS "
+pub const DDS_MAGIC: [u8; 4] = [0x44, 0x44, 0x53, 0x20];
+
+// DDS header and pixel format flags
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+const DDSD_DEPTH: u32 = 0x800000;
+const DDPF_ALPHA: u32 = 0x2;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+
+// DDS caps flags
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALLFACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+
+// DX10 resource dimensions and flags
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+const D3D10_RESOURCE_DIMENSION_TEXTURE3D: u32 = 4;
+const D3D10_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+// DXGI formats matching the bimage ones
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DxgiFormat {
+    Rgba8Unorm     = 28,
+    Rgba8UnormSrgb = 29,
+    R8Unorm        = 61,
+    A8Unorm        = 65,
+    Bc1Unorm       = 71,
+    Bc1UnormSrgb   = 72,
+    Bc3Unorm       = 77,
+    Bc3UnormSrgb   = 78,
+    Bc4Unorm       = 80,
+    Bc5Unorm       = 83,
+    Bc6hUf16       = 95,
+    Bc6hSf16       = 96,
+    Bc7Unorm       = 98,
+    Bc7UnormSrgb   = 99
+}
+
+impl DxgiFormat {
+    // Get DXGI format from its value
+    pub fn from_u32(value: u32) -> Option<DxgiFormat> {
+        match value {
+            28 => Some(DxgiFormat::Rgba8Unorm),
+            29 => Some(DxgiFormat::Rgba8UnormSrgb),
+            61 => Some(DxgiFormat::R8Unorm),
+            65 => Some(DxgiFormat::A8Unorm),
+            71 => Some(DxgiFormat::Bc1Unorm),
+            72 => Some(DxgiFormat::Bc1UnormSrgb),
+            77 => Some(DxgiFormat::Bc3Unorm),
+            78 => Some(DxgiFormat::Bc3UnormSrgb),
+            80 => Some(DxgiFormat::Bc4Unorm),
+            83 => Some(DxgiFormat::Bc5Unorm),
+            95 => Some(DxgiFormat::Bc6hUf16),
+            96 => Some(DxgiFormat::Bc6hSf16),
+            98 => Some(DxgiFormat::Bc7Unorm),
+            99 => Some(DxgiFormat::Bc7UnormSrgb),
+            _ => None
+        }
+    }
+
+    // Get DXGI format from bimage texture format
+    pub fn from_texture_format(format: TextureFormat) -> Option<DxgiFormat> {
+        match format {
+            TextureFormat::FmtRgba8 => Some(DxgiFormat::Rgba8Unorm),
+            TextureFormat::FmtRgba8Srgb => Some(DxgiFormat::Rgba8UnormSrgb),
+            TextureFormat::FmtR8 => Some(DxgiFormat::R8Unorm),
+            TextureFormat::FmtAlpha => Some(DxgiFormat::A8Unorm),
+            TextureFormat::FmtBc1 => Some(DxgiFormat::Bc1Unorm),
+            TextureFormat::FmtBc1Srgb => Some(DxgiFormat::Bc1UnormSrgb),
+            TextureFormat::FmtBc3 => Some(DxgiFormat::Bc3Unorm),
+            TextureFormat::FmtBc3Srgb => Some(DxgiFormat::Bc3UnormSrgb),
+            TextureFormat::FmtBc4 => Some(DxgiFormat::Bc4Unorm),
+            TextureFormat::FmtBc5 => Some(DxgiFormat::Bc5Unorm),
+            TextureFormat::FmtBc6hUf16 => Some(DxgiFormat::Bc6hUf16),
+            TextureFormat::FmtBc6hSf16 => Some(DxgiFormat::Bc6hSf16),
+            TextureFormat::FmtBc7 => Some(DxgiFormat::Bc7Unorm),
+            TextureFormat::FmtBc7Srgb => Some(DxgiFormat::Bc7UnormSrgb),
+            _ => None
+        }
+    }
+
+    // Convert to bimage texture format
+    pub fn as_texture_format(&self) -> TextureFormat {
+        match self {
+            DxgiFormat::Rgba8Unorm => TextureFormat::FmtRgba8,
+            DxgiFormat::Rgba8UnormSrgb => TextureFormat::FmtRgba8Srgb,
+            DxgiFormat::R8Unorm => TextureFormat::FmtR8,
+            DxgiFormat::A8Unorm => TextureFormat::FmtAlpha,
+            DxgiFormat::Bc1Unorm => TextureFormat::FmtBc1,
+            DxgiFormat::Bc1UnormSrgb => TextureFormat::FmtBc1Srgb,
+            DxgiFormat::Bc3Unorm => TextureFormat::FmtBc3,
+            DxgiFormat::Bc3UnormSrgb => TextureFormat::FmtBc3Srgb,
+            DxgiFormat::Bc4Unorm => TextureFormat::FmtBc4,
+            DxgiFormat::Bc5Unorm => TextureFormat::FmtBc5,
+            DxgiFormat::Bc6hUf16 => TextureFormat::FmtBc6hUf16,
+            DxgiFormat::Bc6hSf16 => TextureFormat::FmtBc6hSf16,
+            DxgiFormat::Bc7Unorm => TextureFormat::FmtBc7,
+            DxgiFormat::Bc7UnormSrgb => TextureFormat::FmtBc7Srgb
+        }
+    }
+}
+
+// DDS texture, with its mips already compressed
+// Cubemap faces and array layers are stored one after another, each with its whole mip chain,
+// and every mip of a volume texture holds all its slices
+pub struct DdsTexture {
+    pub width: u32,
+    pub height: u32,
+    // Number of slices of volume textures, or layers of 2D texture arrays
+    pub depth: u32,
+    pub mip_count: u32,
+    pub texture_type: TextureType,
+    pub format: TextureFormat,
+    // Legacy headers can't tell sRGB and linear formats apart
+    pub legacy_header: bool,
+    pub data: Vec<u8>
+}
+
+impl DdsTexture {
+    // Parse DDS file bytes, supporting both legacy and DX10 headers
+    pub fn from_bytes(bytes: &[u8]) -> Result<DdsTexture, String> {
+        if bytes.len() < 128 || bytes[0..4] != DDS_MAGIC {
+            return Err("not a DDS file".into());
+        }
+
+        let read_u32 = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        // Header
+        let flags = read_u32(8);
+        let height = read_u32(12);
+        let width = read_u32(16);
+        let mip_count = match read_u32(28) {
+            count if flags & DDSD_MIPMAPCOUNT != 0 && count > 0 => count,
+            _ => 1
+        };
+
+        // Pixel format
+        let pf_flags = read_u32(80);
+        let four_cc = &bytes[84..88];
+        let rgb_bit_count = read_u32(88);
+        let masks = [read_u32(92), read_u32(96), read_u32(100), read_u32(104)];
+
+        let caps2 = read_u32(112);
+
+        if caps2 & (DDSCAPS2_CUBEMAP | DDSCAPS2_VOLUME) != 0 {
+            return Err("cubemap and volume DDS files are not supported".into());
+        }
+
+        let (format, data_offset) = match () {
+            _ if pf_flags & DDPF_FOURCC != 0 && four_cc == b"DX10" => {
+                if bytes.len() < 148 {
+                    return Err("DDS file is truncated".into());
+                }
+
+                // DX10 header
+                let dxgi_format = read_u32(128);
+                let array_size = read_u32(140);
+
+                if array_size > 1 {
+                    return Err("array DDS files are not supported".into());
+                }
+
+                match DxgiFormat::from_u32(dxgi_format) {
+                    Some(dxgi_format) => (dxgi_format.as_texture_format(), 148),
+                    None => return Err(format!("unsupported DXGI format {}", dxgi_format))
+                }
+            },
+            _ if pf_flags & DDPF_FOURCC != 0 => {
+                let format = match four_cc {
+                    b"DXT1" => TextureFormat::FmtBc1,
+                    b"DXT5" => TextureFormat::FmtBc3,
+                    b"ATI1" | b"BC4U" => TextureFormat::FmtBc4,
+                    b"ATI2" | b"BC5U" => TextureFormat::FmtBc5,
+                    _ => {
+                        return Err(format!(
+                            "unsupported FourCC '{}'",
+                            String::from_utf8_lossy(four_cc)
+                        ))
+                    },
+                };
+
+                (format, 128)
+            },
+            _ if pf_flags & DDPF_RGB != 0
+                && rgb_bit_count == 32
+                && masks == [0xFF, 0xFF00, 0xFF0000, 0xFF000000] =>
+            {
+                (TextureFormat::FmtRgba8, 128)
+            },
+            _ if pf_flags & DDPF_ALPHA != 0 && rgb_bit_count == 8 => (TextureFormat::FmtAlpha, 128),
+            _ => return Err("unsupported pixel format".into())
+        };
+
+        Ok(DdsTexture {
+            width,
+            height,
+            depth: 1,
+            mip_count,
+            texture_type: TextureType::Tt2d,
+            format,
+            legacy_header: data_offset == 128,
+            data: bytes[data_offset..].to_vec()
+        })
+    }
+
+    // Convert DdsTexture to DDS file bytes, always using a DX10 header
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0_u8; 148];
+
+        let mut write_u32 = |offset: usize, value: u32| {
+            bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+
+        // Block compressed formats give the size of the first mip, uncompressed ones the row pitch
+        let (pitch_flag, pitch_or_linear_size) = match self.format {
+            TextureFormat::FmtRgba8
+            | TextureFormat::FmtRgba8Srgb
+            | TextureFormat::FmtR8
+            | TextureFormat::FmtAlpha => (DDSD_PITCH, self.format.mip_size(self.width, 1).unwrap()),
+            _ => (
+                DDSD_LINEARSIZE,
+                self.format.mip_size(self.width, self.height).unwrap()
+            )
+        };
+
+        let caps = match self.mip_count {
+            1 => DDSCAPS_TEXTURE,
+            _ => DDSCAPS_TEXTURE | DDSCAPS_COMPLEX | DDSCAPS_MIPMAP
+        };
+
+        // Depth flag, extra caps, resource dimension, misc flag and array size for each texture type
+        let (depth_flag, caps2, dimension, misc_flag, array_size) = match self.texture_type {
+            TextureType::Tt2d => (0, 0, D3D10_RESOURCE_DIMENSION_TEXTURE2D, 0, self.depth.max(1)),
+            TextureType::Tt3d => (
+                DDSD_DEPTH,
+                DDSCAPS2_VOLUME,
+                D3D10_RESOURCE_DIMENSION_TEXTURE3D,
+                0,
+                1
+            ),
+            TextureType::TtCubic => (
+                0,
+                DDSCAPS2_CUBEMAP | DDSCAPS2_CUBEMAP_ALLFACES,
+                D3D10_RESOURCE_DIMENSION_TEXTURE2D,
+                D3D10_RESOURCE_MISC_TEXTURECUBE,
+                1
+            )
+        };
+
+        let caps = match caps2 {
+            0 => caps,
+            _ => caps | DDSCAPS_COMPLEX
+        };
+
+        // Header
+        write_u32(4, 124);
+        write_u32(
+            8,
+            DDSD_CAPS
+                | DDSD_HEIGHT
+                | DDSD_WIDTH
+                | DDSD_PIXELFORMAT
+                | DDSD_MIPMAPCOUNT
+                | pitch_flag
+                | depth_flag
+        );
+        write_u32(12, self.height);
+        write_u32(16, self.width);
+        write_u32(20, pitch_or_linear_size as u32);
+
+        if depth_flag != 0 {
+            write_u32(24, self.depth);
+        }
+
+        write_u32(28, self.mip_count);
+
+        // Pixel format
+        write_u32(76, 32);
+        write_u32(80, DDPF_FOURCC);
+        write_u32(84, u32::from_le_bytes(*b"DX10"));
+        write_u32(108, caps);
+        write_u32(112, caps2);
+
+        // DX10 header
+        write_u32(128, DxgiFormat::from_texture_format(self.format).unwrap() as u32);
+        write_u32(132, dimension);
+        write_u32(136, misc_flag);
+        write_u32(140, array_size);
+
+        bytes[0..4].copy_from_slice(&DDS_MAGIC);
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+}
+
+// Read DDS file, returns None if the file isn't a DDS
+pub fn read_dds(path: &Path) -> Option<Result<DdsTexture, String>> {
+    let bytes = fs::read(path).ok()?;
+
+    if !bytes.starts_with(&DDS_MAGIC) {
+        return None;
+    }
+
+    Some(DdsTexture::from_bytes(&bytes))
+}
@@ -0,0 +1,82 @@
+// Offsets to the 8 neighbors of a pixel
+const NEIGHBORS: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1)
+];
+
+// Get the indices of the pixels around the given one, inside the image
+fn neighbors(index: usize, width: usize, height: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = ((index % width) as i64, (index / width) as i64);
+
+    NEIGHBORS.iter().filter_map(move |(dx, dy)| {
+        let (neighbor_x, neighbor_y) = (x + dx, y + dy);
+
+        match neighbor_x >= 0 && neighbor_y >= 0 && neighbor_x < width as i64 && neighbor_y < height as i64 {
+            true => Some(neighbor_y as usize * width + neighbor_x as usize),
+            false => None
+        }
+    })
+}
+
+// Fill the color of fully transparent pixels in RGBA bytes, growing it out of the visible ones ring by ring
+// Every new pixel gets the average color of its already filled neighbors, and alpha is left as is
+pub fn dilate_transparent(pixels: &mut [u8], width: u32, height: u32) {
+    let (width, height) = (width as usize, height as usize);
+    let mut filled: Vec<bool> = pixels.chunks_exact(4).map(|pixel| pixel[3] > 0).collect();
+
+    // Nothing to grow from, or nothing to fill
+    if filled.iter().all(|filled| !filled) || filled.iter().all(|filled| *filled) {
+        return;
+    }
+
+    let mut queued = filled.clone();
+    let mut ring: Vec<usize> = (0..filled.len())
+        .filter(|i| !filled[*i] && neighbors(*i, width, height).any(|neighbor| filled[neighbor]))
+        .collect();
+
+    for i in &ring {
+        queued[*i] = true;
+    }
+
+    while !ring.is_empty() {
+        // Get the colors of the whole ring before marking it, so it only grows from the previous rings
+        let colors: Vec<[u8; 3]> = ring
+            .iter()
+            .map(|i| {
+                let (mut sum, mut count) = ([0_u32; 3], 0);
+
+                for neighbor in neighbors(*i, width, height).filter(|neighbor| filled[*neighbor]) {
+                    for (channel, total) in sum.iter_mut().enumerate() {
+                        *total += pixels[neighbor * 4 + channel] as u32;
+                    }
+
+                    count += 1;
+                }
+
+                sum.map(|total| ((total + count / 2) / count) as u8)
+            })
+            .collect();
+
+        let mut next_ring = Vec::new();
+
+        for (i, color) in ring.iter().zip(colors) {
+            pixels[i * 4..i * 4 + 3].copy_from_slice(&color);
+            filled[*i] = true;
+
+            for neighbor in neighbors(*i, width, height) {
+                if !queued[neighbor] {
+                    queued[neighbor] = true;
+                    next_ring.push(neighbor);
+                }
+            }
+        }
+
+        ring = next_ring;
+    }
+}
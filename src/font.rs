@@ -0,0 +1,228 @@
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use image::RgbaImage;
+
+use crate::sdf;
+
+// Glyphs are rasterized this many times bigger than their final size, to generate their distance field
+const OVERSAMPLING: u32 = 8;
+
+// Position of a glyph in the atlas and its metrics, in atlas pixels
+// Offsets go from the pen position on the baseline to the top-left corner of the glyph, with y pointing down
+pub struct GlyphMetrics {
+    pub character: char,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: f32,
+    pub y_offset: f32,
+    pub advance: f32
+}
+
+// Distance field atlas of a font's glyphs
+pub struct FontAtlas {
+    pub image: RgbaImage,
+    pub glyphs: Vec<GlyphMetrics>,
+    pub size: u32,
+    pub spread: f32,
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32
+}
+
+impl FontAtlas {
+    // Describe the glyphs as JSON, for the sidecar file
+    pub fn to_json(&self) -> String {
+        let glyphs: Vec<String> = self
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                format!(
+                    "    {{ \"char\": {}, \"x\": {}, \"y\": {}, \"width\": {}, \"height\": {}, \
+                     \"x_offset\": {}, \"y_offset\": {}, \"advance\": {} }}",
+                    glyph.character as u32,
+                    glyph.x,
+                    glyph.y,
+                    glyph.width,
+                    glyph.height,
+                    glyph.x_offset,
+                    glyph.y_offset,
+                    glyph.advance
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\n  \"size\": {},\n  \"spread\": {},\n  \"ascent\": {},\n  \"descent\": {},\n  \"line_gap\": \
+             {},\n  \"glyphs\": [\n{}\n  ]\n}}\n",
+            self.size,
+            self.spread,
+            self.ascent,
+            self.descent,
+            self.line_gap,
+            glyphs.join(",\n")
+        )
+    }
+}
+
+// Parse a character set made of comma separated code points and ranges, e.g. '32-126,169'
+pub fn parse_charset(charset: &str) -> Result<Vec<char>, String> {
+    let mut chars = Vec::new();
+
+    for part in charset.split(',') {
+        let (start, end) = part.split_once('-').unwrap_or((part, part));
+
+        let (start, end) = match (start.trim().parse::<u32>(), end.trim().parse::<u32>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end),
+            _ => return Err(format!("Invalid character range '{}'", part))
+        };
+
+        chars.extend((start..=end).filter_map(char::from_u32));
+    }
+
+    chars.sort_unstable();
+    chars.dedup();
+    Ok(chars)
+}
+
+// Pack rectangles into rows of the given width, tallest first
+// Returns the position of every rectangle and the total height, or None if a rectangle is wider than a row
+fn pack_shelves(sizes: &[(u32, u32)], width: u32) -> Option<(Vec<(u32, u32)>, u32)> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|i| (u32::MAX - sizes[*i].1, *i));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+
+    for i in order {
+        let (rect_width, rect_height) = sizes[i];
+
+        if rect_width > width {
+            return None;
+        }
+
+        // Start a new row
+        if x + rect_width > width {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+
+        positions[i] = (x, y);
+        x += rect_width;
+        row_height = row_height.max(rect_height);
+    }
+
+    Some((positions, y + row_height))
+}
+
+// Rasterize the given characters into a distance field atlas, with glyphs of the given size in pixels
+// Characters missing from the font are skipped
+pub fn build_font_atlas(
+    font_bytes: &[u8], chars: &[char], size: u32, spread: f32
+) -> Result<FontAtlas, String> {
+    let font = FontRef::try_from_slice(font_bytes).map_err(|e| e.to_string())?;
+    let scale = PxScale::from((size * OVERSAMPLING) as f32);
+    let scaled_font = font.as_scaled(scale);
+
+    // Room around every glyph for its distance field, in oversampled pixels
+    let padding = (spread.ceil() as u32 + 1) * OVERSAMPLING;
+
+    let mut glyphs = Vec::new();
+    let mut glyph_images = Vec::new();
+
+    for character in chars {
+        let glyph_id = font.glyph_id(*character);
+
+        if glyph_id.0 == 0 {
+            continue;
+        }
+
+        let advance = scaled_font.h_advance(glyph_id) / OVERSAMPLING as f32;
+
+        // Whitespace has no outline, only an advance
+        let outlined = match font.outline_glyph(glyph_id.with_scale(scale)) {
+            Some(outlined) => outlined,
+            None => {
+                glyphs.push(GlyphMetrics {
+                    character: *character,
+                    x: 0,
+                    y: 0,
+                    width: 0,
+                    height: 0,
+                    x_offset: 0.0,
+                    y_offset: 0.0,
+                    advance
+                });
+
+                continue;
+            }
+        };
+
+        // Rasterize the glyph's coverage, padded to a whole number of atlas pixels
+        let bounds = outlined.px_bounds();
+        let width = (bounds.width() as u32 + 2 * padding).next_multiple_of(OVERSAMPLING);
+        let height = (bounds.height() as u32 + 2 * padding).next_multiple_of(OVERSAMPLING);
+        let mut glyph_img = RgbaImage::new(width, height);
+
+        outlined.draw(|x, y, coverage| {
+            let alpha = (coverage * 255.0).round().clamp(0.0, 255.0) as u8;
+            glyph_img.put_pixel(x + padding, y + padding, image::Rgba([255, 255, 255, alpha]));
+        });
+
+        glyphs.push(GlyphMetrics {
+            character: *character,
+            x: 0,
+            y: 0,
+            width: width / OVERSAMPLING,
+            height: height / OVERSAMPLING,
+            x_offset: (bounds.min.x - padding as f32) / OVERSAMPLING as f32,
+            y_offset: (bounds.min.y - padding as f32) / OVERSAMPLING as f32,
+            advance
+        });
+
+        glyph_images.push((
+            glyphs.len() - 1,
+            sdf::generate_sdf(&glyph_img, spread, width / OVERSAMPLING, height / OVERSAMPLING)
+        ));
+    }
+
+    if glyph_images.is_empty() {
+        return Err("None of the characters have a visible glyph in the font".into());
+    }
+
+    // Find the smallest power of two width that gives an atlas no taller than it is wide
+    let sizes: Vec<(u32, u32)> = glyph_images
+        .iter()
+        .map(|(_, glyph_img)| glyph_img.dimensions())
+        .collect();
+    let mut atlas_width = 64;
+
+    let (positions, atlas_height) = loop {
+        match pack_shelves(&sizes, atlas_width) {
+            Some((positions, height)) if height <= atlas_width => {
+                break (positions, height.next_power_of_two())
+            },
+            _ => atlas_width *= 2
+        }
+    };
+
+    // Copy glyphs into the atlas, the rest stays fully outside
+    let mut atlas_img = RgbaImage::new(atlas_width, atlas_height);
+
+    for ((glyph_index, glyph_img), (x, y)) in glyph_images.iter().zip(positions) {
+        image::imageops::replace(&mut atlas_img, glyph_img, x as i64, y as i64);
+        glyphs[*glyph_index].x = x;
+        glyphs[*glyph_index].y = y;
+    }
+
+    Ok(FontAtlas {
+        image: atlas_img,
+        glyphs,
+        size,
+        spread,
+        ascent: scaled_font.ascent() / OVERSAMPLING as f32,
+        descent: scaled_font.descent() / OVERSAMPLING as f32,
+        line_gap: scaled_font.line_gap() / OVERSAMPLING as f32
+    })
+}
@@ -0,0 +1,241 @@
+use crate::bim::TextureFormat;
+
+// KTX2 identifier, "«KTX 20»\r\n\x1A\n"
+pub const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A
+];
+
+// Data format descriptor color models
+const KHR_DF_MODEL_RGBSDA: u8 = 1;
+const KHR_DF_MODEL_BC1A: u8 = 128;
+const KHR_DF_MODEL_BC3: u8 = 130;
+const KHR_DF_MODEL_BC4: u8 = 131;
+const KHR_DF_MODEL_BC5: u8 = 132;
+const KHR_DF_MODEL_BC6H: u8 = 133;
+const KHR_DF_MODEL_BC7: u8 = 134;
+
+// Data format descriptor transfer functions
+const KHR_DF_TRANSFER_LINEAR: u8 = 1;
+const KHR_DF_TRANSFER_SRGB: u8 = 2;
+
+// Data format descriptor sample channels and qualifiers
+const KHR_DF_CHANNEL_RED: u8 = 0;
+const KHR_DF_CHANNEL_GREEN: u8 = 1;
+const KHR_DF_CHANNEL_BLUE: u8 = 2;
+const KHR_DF_CHANNEL_ALPHA: u8 = 15;
+const KHR_DF_SAMPLE_DATATYPE_LINEAR: u8 = 0x10;
+const KHR_DF_SAMPLE_DATATYPE_SIGNED: u8 = 0x40;
+const KHR_DF_SAMPLE_DATATYPE_FLOAT: u8 = 0x80;
+
+// Vulkan formats matching the bimage ones
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum VkFormat {
+    R8Unorm       = 9,
+    R8g8b8a8Unorm = 37,
+    R8g8b8a8Srgb  = 43,
+    Bc1RgbUnorm   = 131,
+    Bc1RgbSrgb    = 132,
+    Bc3Unorm      = 137,
+    Bc3Srgb       = 138,
+    Bc4Unorm      = 139,
+    Bc5Unorm      = 141,
+    Bc6hUfloat    = 143,
+    Bc6hSfloat    = 144,
+    Bc7Unorm      = 145,
+    Bc7Srgb       = 146
+}
+
+impl VkFormat {
+    // Get Vulkan format from bimage texture format
+    pub fn from_texture_format(format: TextureFormat) -> Option<VkFormat> {
+        match format {
+            // There's no alpha-only format in core Vulkan
+            TextureFormat::FmtAlpha | TextureFormat::FmtR8 => Some(VkFormat::R8Unorm),
+            TextureFormat::FmtRgba8 => Some(VkFormat::R8g8b8a8Unorm),
+            TextureFormat::FmtRgba8Srgb => Some(VkFormat::R8g8b8a8Srgb),
+            TextureFormat::FmtBc1 => Some(VkFormat::Bc1RgbUnorm),
+            TextureFormat::FmtBc1Srgb => Some(VkFormat::Bc1RgbSrgb),
+            TextureFormat::FmtBc3 => Some(VkFormat::Bc3Unorm),
+            TextureFormat::FmtBc3Srgb => Some(VkFormat::Bc3Srgb),
+            TextureFormat::FmtBc4 => Some(VkFormat::Bc4Unorm),
+            TextureFormat::FmtBc5 => Some(VkFormat::Bc5Unorm),
+            TextureFormat::FmtBc6hUf16 => Some(VkFormat::Bc6hUfloat),
+            TextureFormat::FmtBc6hSf16 => Some(VkFormat::Bc6hSfloat),
+            TextureFormat::FmtBc7 => Some(VkFormat::Bc7Unorm),
+            TextureFormat::FmtBc7Srgb => Some(VkFormat::Bc7Srgb),
+            _ => None
+        }
+    }
+
+    // Check if the format uses the sRGB transfer function
+    pub fn is_srgb(&self) -> bool {
+        matches!(
+            self,
+            VkFormat::R8g8b8a8Srgb | VkFormat::Bc1RgbSrgb | VkFormat::Bc3Srgb | VkFormat::Bc7Srgb
+        )
+    }
+}
+
+// KTX2 texture, with its mip levels already compressed
+// Every level holds all its array layers, cubemap faces and volume slices, in that order
+pub struct Ktx2Texture {
+    pub width: u32,
+    pub height: u32,
+    // 0 for anything but volume textures and texture arrays
+    pub depth: u32,
+    pub layers: u32,
+    pub faces: u32,
+    pub format: TextureFormat,
+    pub levels: Vec<Vec<u8>>
+}
+
+impl Ktx2Texture {
+    // Build the basic data format descriptor for the texture's format
+    fn data_format_descriptor(&self, vk_format: VkFormat) -> Vec<u8> {
+        // Color model, texel block size in bytes and samples (bit offset, bit length, channel)
+        let (color_model, block_bytes, samples): (u8, u8, &[(u16, u8, u8)]) = match vk_format {
+            VkFormat::R8Unorm => (KHR_DF_MODEL_RGBSDA, 1, &[(0, 8, KHR_DF_CHANNEL_RED)]),
+            VkFormat::R8g8b8a8Unorm | VkFormat::R8g8b8a8Srgb => (
+                KHR_DF_MODEL_RGBSDA,
+                4,
+                &[
+                    (0, 8, KHR_DF_CHANNEL_RED),
+                    (8, 8, KHR_DF_CHANNEL_GREEN),
+                    (16, 8, KHR_DF_CHANNEL_BLUE),
+                    (24, 8, KHR_DF_CHANNEL_ALPHA)
+                ]
+            ),
+            VkFormat::Bc1RgbUnorm | VkFormat::Bc1RgbSrgb => (KHR_DF_MODEL_BC1A, 8, &[(0, 64, 0)]),
+            VkFormat::Bc3Unorm | VkFormat::Bc3Srgb => (
+                KHR_DF_MODEL_BC3,
+                16,
+                &[(0, 64, KHR_DF_CHANNEL_ALPHA), (64, 64, 0)]
+            ),
+            VkFormat::Bc4Unorm => (KHR_DF_MODEL_BC4, 8, &[(0, 64, 0)]),
+            VkFormat::Bc5Unorm => (KHR_DF_MODEL_BC5, 16, &[(0, 64, 0), (64, 64, 1)]),
+            VkFormat::Bc6hUfloat | VkFormat::Bc6hSfloat => (KHR_DF_MODEL_BC6H, 16, &[(0, 128, 0)]),
+            VkFormat::Bc7Unorm | VkFormat::Bc7Srgb => (KHR_DF_MODEL_BC7, 16, &[(0, 128, 0)])
+        };
+
+        let is_block_compressed = color_model != KHR_DF_MODEL_RGBSDA;
+        let transfer_function = match vk_format.is_srgb() {
+            true => KHR_DF_TRANSFER_SRGB,
+            false => KHR_DF_TRANSFER_LINEAR
+        };
+
+        let block_size = 24 + 16 * samples.len();
+        let mut dfd = Vec::with_capacity(4 + block_size);
+
+        // Total size, vendor id and descriptor type, version and block size
+        dfd.extend_from_slice(&(4 + block_size as u32).to_le_bytes());
+        dfd.extend_from_slice(&0_u32.to_le_bytes());
+        dfd.extend_from_slice(&2_u16.to_le_bytes());
+        dfd.extend_from_slice(&(block_size as u16).to_le_bytes());
+
+        // Color model, BT.709 primaries, transfer function and straight alpha
+        dfd.extend_from_slice(&[color_model, 1, transfer_function, 0]);
+
+        // Texel block dimensions (minus one), and bytes per plane
+        match is_block_compressed {
+            true => dfd.extend_from_slice(&[3, 3, 0, 0]),
+            false => dfd.extend_from_slice(&[0, 0, 0, 0])
+        }
+
+        dfd.extend_from_slice(&[block_bytes, 0, 0, 0, 0, 0, 0, 0]);
+
+        for (bit_offset, bit_length, channel) in samples {
+            // Alpha is always linear, and BC6H is made of floats
+            let channel_type = match vk_format {
+                VkFormat::Bc6hUfloat => channel | KHR_DF_SAMPLE_DATATYPE_FLOAT,
+                VkFormat::Bc6hSfloat => {
+                    channel | KHR_DF_SAMPLE_DATATYPE_FLOAT | KHR_DF_SAMPLE_DATATYPE_SIGNED
+                },
+                _ if *channel == KHR_DF_CHANNEL_ALPHA && vk_format.is_srgb() => {
+                    channel | KHR_DF_SAMPLE_DATATYPE_LINEAR
+                },
+                _ => *channel
+            };
+
+            let (sample_lower, sample_upper) = match vk_format {
+                VkFormat::Bc6hUfloat => (0_u32, 1.0_f32.to_bits()),
+                VkFormat::Bc6hSfloat => ((-1.0_f32).to_bits(), 1.0_f32.to_bits()),
+                _ if is_block_compressed => (0, u32::MAX),
+                _ => (0, 255)
+            };
+
+            dfd.extend_from_slice(&bit_offset.to_le_bytes());
+            dfd.extend_from_slice(&[bit_length - 1, channel_type]);
+            dfd.extend_from_slice(&[0, 0, 0, 0]);
+            dfd.extend_from_slice(&sample_lower.to_le_bytes());
+            dfd.extend_from_slice(&sample_upper.to_le_bytes());
+        }
+
+        dfd
+    }
+
+    // Convert Ktx2Texture to KTX2 file bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let vk_format = VkFormat::from_texture_format(self.format).unwrap();
+        let dfd = self.data_format_descriptor(vk_format);
+
+        // Levels must be aligned to the texel block size, and to 4 bytes
+        let alignment = match vk_format {
+            VkFormat::R8Unorm | VkFormat::R8g8b8a8Unorm | VkFormat::R8g8b8a8Srgb => 4,
+            VkFormat::Bc1RgbUnorm | VkFormat::Bc1RgbSrgb | VkFormat::Bc4Unorm => 8,
+            _ => 16
+        };
+
+        let level_count = self.levels.len();
+        let dfd_offset = 80 + 24 * level_count;
+        let mut bytes =
+            Vec::with_capacity(dfd_offset + dfd.len() + self.levels.iter().map(Vec::len).sum::<usize>());
+
+        // Header: format, type size, dimensions, layer, face and level count, and supercompression
+        bytes.extend_from_slice(&KTX2_IDENTIFIER);
+
+        for value in [
+            vk_format as u32,
+            1,
+            self.width,
+            self.height,
+            self.depth,
+            self.layers,
+            self.faces,
+            level_count as u32,
+            0
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        // Index, with no key/value or supercompression data
+        bytes.extend_from_slice(&(dfd_offset as u32).to_le_bytes());
+        bytes.extend_from_slice(&(dfd.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&[0_u8; 8 + 16]);
+
+        // Level data goes after the descriptor, from the smallest level to the largest
+        let mut level_offsets = vec![0_usize; level_count];
+        let mut offset = dfd_offset + dfd.len();
+
+        for (level, level_bytes) in self.levels.iter().enumerate().rev() {
+            offset = offset.next_multiple_of(alignment);
+            level_offsets[level] = offset;
+            offset += level_bytes.len();
+        }
+
+        // Level index, from the largest level to the smallest
+        for (level, level_bytes) in self.levels.iter().enumerate() {
+            bytes.extend_from_slice(&(level_offsets[level] as u64).to_le_bytes());
+            bytes.extend_from_slice(&(level_bytes.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&(level_bytes.len() as u64).to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&dfd);
+
+        for (level, level_bytes) in self.levels.iter().enumerate().rev() {
+            bytes.resize(level_offsets[level], 0);
+            bytes.extend_from_slice(level_bytes);
+        }
+
+        bytes
+    }
+}
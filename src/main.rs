@@ -1,26 +1,55 @@
+mod atlas;
 mod bc7e;
 mod bim;
+mod channels;
+mod cubemap;
+mod dds;
+mod dilate;
+mod font;
+mod ktx2;
+mod normals;
 mod ooz;
+mod pbr;
+mod sdf;
+mod svg;
 mod utils;
 
 use std::{
-    cmp, env,
+    cmp,
+    collections::HashMap,
+    env,
     fmt::Write as _,
-    fs::File,
-    io::Write as _,
-    mem,
+    fs::{self, File},
+    io::{Cursor, Write as _},
+    iter,
     path::{Path, PathBuf},
     process,
     sync::{Arc, Mutex},
     thread
 };
 
+use atlas::AtlasLayout;
 use bc7e::CompressBlockParams;
-use bim::{BIMHeader, BIMMipMap, TextureFormat, TextureMaterialKind};
+use bim::{
+    BIMHeader, BIMMipMap, TextureFormat, TextureMaterialKind, TextureType, BIM_HEADER_SIZE, BIM_MIP_MAP_SIZE
+};
+use channels::{Channel, PackRecipe};
+use cubemap::SampleFilter;
+use dds::{DdsTexture, DxgiFormat};
 use fast_image_resize::{images::Image, MulDiv, PixelType, Resizer};
-use image::{ImageFormat, ImageReader, RgbaImage};
+use font::FontAtlas;
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder},
+    AnimationDecoder, DynamicImage, Frame, ImageFormat, RgbaImage
+};
+use ktx2::{Ktx2Texture, VkFormat};
+use normals::HeightFilter;
+use pbr::PbrRecipe;
 use texpresso::{Algorithm, Params};
 
+// Magic of kraken compressed files, "DIVINITY"
+const KRAKEN_MAGIC: [u8; 8] = [0x44, 0x49, 0x56, 0x49, 0x4E, 0x49, 0x54, 0x59];
+
 // Compress data with oodle's kraken
 fn kraken_compress(vec: &mut Vec<u8>) -> Result<Vec<u8>, String> {
     // Create output byte vec
@@ -28,7 +57,7 @@ fn kraken_compress(vec: &mut Vec<u8>) -> Result<Vec<u8>, String> {
     let mut comp_vec = vec![0_u8; comp_len as usize + 16];
 
     // Add magic and decompressed size
-    comp_vec[0..8].copy_from_slice(&[0x44, 0x49, 0x56, 0x49, 0x4E, 0x49, 0x54, 0x59]);
+    comp_vec[0..8].copy_from_slice(&KRAKEN_MAGIC);
     comp_vec[8..16].copy_from_slice(&(vec.len() as u64).to_le_bytes());
 
     // Compress using ooz
@@ -46,6 +75,52 @@ fn kraken_compress(vec: &mut Vec<u8>) -> Result<Vec<u8>, String> {
     }
 }
 
+// Decompress data compressed with oodle's kraken
+fn kraken_decompress(vec: &[u8]) -> Result<Vec<u8>, String> {
+    if vec.len() < 16 || vec[0..8] != KRAKEN_MAGIC {
+        return Err("Invalid kraken header".into());
+    }
+
+    // ooz reports the decompressed size as an i32, so anything bigger can only come from a corrupt header
+    let dec_len = u64::from_le_bytes(vec[8..16].try_into().unwrap());
+
+    if dec_len > i32::MAX as u64 {
+        return Err(format!("Invalid decompressed size {} in kraken header", dec_len));
+    }
+
+    // Create output byte vec, with some extra space needed by ooz
+    // The size isn't checked against the bimage's own yet, so fail instead of aborting if it can't be allocated
+    let dec_len = dec_len as usize;
+    let mut dec_vec = Vec::new();
+
+    if dec_vec.try_reserve_exact(dec_len + 64).is_err() {
+        return Err(format!("Not enough memory to decompress {} bytes", dec_len));
+    }
+
+    dec_vec.resize(dec_len + 64, 0);
+
+    // Decompress using ooz
+    let result;
+
+    unsafe {
+        result = ooz::kraken_decompress(
+            vec.as_ptr().add(16),
+            vec.len() - 16,
+            dec_vec.as_mut_ptr(),
+            dec_len
+        );
+    }
+
+    if result == dec_len as i32 {
+        // Cut off extra bytes
+        dec_vec.truncate(dec_len);
+        Ok(dec_vec)
+    }
+    else {
+        Err("Failed to decompress texture using ooz".into())
+    }
+}
+
 // Compress into BCn format
 fn compress_bcn(format: TextureFormat, image: &[u8], width: usize, height: usize) -> Vec<u8> {
     match format {
@@ -112,151 +187,271 @@ fn compress_bcn(format: TextureFormat, image: &[u8], width: usize, height: usize
     }
 }
 
-// Convert texture to bimage format used by the game
-fn convert_to_bimage(
-    src_img: RgbaImage, file_name: String, stripped_file_name: String, format: TextureFormat, compress: bool
-) -> Result<Vec<u8>, String> {
-    // Get width and height
-    let (width, height) = src_img.dimensions();
+// Get mipmap count from the texture size and the '$nomips', '$mips=N' and '$minmip=N' tokens
+fn get_mipmap_count(file_name: &str, width: u32, height: u32) -> Result<u32, String> {
+    if file_name.contains("$nomips") {
+        return Ok(1);
+    }
 
-    // Get mipmap count
-    let mipmap_count = match () {
-        _ if file_name.contains("$nomips") => 1,
-        _ => 1 + f64::from(cmp::max(width, height)).log2() as u32
-    };
+    // Full mip chain, down to 1x1
+    let mut mipmap_count = 1 + f64::from(cmp::max(width, height)).log2() as u32;
 
-    // Get upper limit for total texture size (with mipmaps)
-    // Derived from sumation of mipmap approx
-    let four_power = 4_u32.pow(mipmap_count) as usize;
-    let block_size = format.block_size().unwrap() as usize;
-    let added_texture_approx =
-        (4 * block_size * ((width as usize + 3) / 4) * ((height as usize + 3) / 4) * (four_power - 1))
-            / (3 * four_power)
-            + block_size * 3;
+    // Cap the number of levels
+    if let Some(max_mipmap_count) = utils::parse_token::<u32>(file_name, "mips")? {
+        if max_mipmap_count == 0 {
+            return Err("Mip count must be at least 1".into());
+        }
 
-    // BIM bytes
-    let mut texture = Vec::with_capacity(added_texture_approx);
-    let mut bim = Vec::with_capacity(
-        mem::size_of::<BIMHeader>()
-            + mem::size_of::<BIMMipMap>() * mipmap_count as usize
-            + added_texture_approx
-    );
+        mipmap_count = cmp::min(mipmap_count, max_mipmap_count);
+    }
 
-    // Create BIM header and append it to bim
-    bim.extend_from_slice(
-        &BIMHeader {
-            pixel_width: width,
-            pixel_height: height,
-            mip_count: mipmap_count,
-            texture_format: format as u32,
-            texture_material_kind: TextureMaterialKind::from_filename(file_name, stripped_file_name, format)
-                as u32,
-            ..Default::default()
+    // Drop the levels smaller than the minimum size
+    if let Some(min_mip_size) = utils::parse_token::<u32>(file_name, "minmip")? {
+        while mipmap_count > 1 {
+            let (mip_width, mip_height) = mip_dimensions(width, height, mipmap_count - 1);
+
+            if cmp::min(mip_width, mip_height) >= min_mip_size {
+                break;
+            }
+
+            mipmap_count -= 1;
         }
-        .to_bytes()
-    );
+    }
+
+    Ok(mipmap_count)
+}
+
+// Get the padding around every frame of a flipbook atlas, from the '$padding=N' token
+fn get_atlas_padding(file_name: &str) -> Result<u32, String> {
+    match utils::parse_token::<u32>(file_name, "padding")? {
+        Some(padding) if padding > i16::MAX as u32 => Err(format!("Atlas padding {} is too big", padding)),
+        padding => Ok(padding.unwrap_or(0))
+    }
+}
+
+// Get the dimensions of the given mip level
+fn mip_dimensions(width: u32, height: u32, level: u32) -> (u32, u32) {
+    // Make sure they're not 0
+    (cmp::max(width >> level, 1), cmp::max(height >> level, 1))
+}
+
+// Pad RGBA image to a multiple of 4 in both dimensions by repeating the last column and row
+fn pad_to_block_size(image: Vec<u8>, width: u32, height: u32) -> (Vec<u8>, u32, u32) {
+    let padded_width = (width + 3) & !3;
+    let padded_height = (height + 3) & !3;
+
+    // Nothing to pad
+    if padded_width == width && padded_height == height {
+        return (image, width, height);
+    }
+
+    let stride = width as usize * 4;
+    let padded_stride = padded_width as usize * 4;
+    let mut padded = Vec::with_capacity(padded_stride * padded_height as usize);
+
+    for row in image.chunks_exact(stride) {
+        // Copy row and repeat its last pixel
+        padded.extend_from_slice(row);
+
+        for _ in width..padded_width {
+            padded.extend_from_slice(&row[stride - 4..]);
+        }
+    }
+
+    // Repeat the last padded row
+    let last_row_start = padded.len() - padded_stride;
+
+    for _ in height..padded_height {
+        padded.extend_from_within(last_row_start..last_row_start + padded_stride);
+    }
+
+    (padded, padded_width, padded_height)
+}
+
+// Generate every mip by resizing the previous level, returning their RGBA bytes
+fn generate_mips_cascade(
+    src_img: RgbaImage, mut custom_mips: HashMap<u32, RgbaImage>, mipmap_count: u32, premultiplied: bool
+) -> Vec<Vec<u8>> {
+    let (width, height) = src_img.dimensions();
+    let mut mips = Vec::with_capacity(mipmap_count as usize);
+
+    // Mip 0 is the source image itself
+    if !premultiplied {
+        mips.push(src_img.as_raw().clone());
+    }
+
+    // Multiply RGB by alpha (needed for resize algorithm)
+    let alpha_mul_div = MulDiv::default();
+    let mut prev_mip = Image::from_vec_u8(width, height, src_img.into_raw(), PixelType::U8x4).unwrap();
+    alpha_mul_div.multiply_alpha_inplace(&mut prev_mip).unwrap();
+
+    if premultiplied {
+        mips.push(prev_mip.buffer().to_vec());
+    }
+
+    let mut resizer = Resizer::new();
+
+    for level in 1..mipmap_count {
+        let (mip_width, mip_height) = mip_dimensions(width, height, level);
+
+        let mip = match custom_mips.remove(&level) {
+            Some(mip_img) => {
+                // Use the user-supplied mip as is, and keep cascading from it
+                if !premultiplied {
+                    mips.push(mip_img.as_raw().clone());
+                }
+
+                let mut mip =
+                    Image::from_vec_u8(mip_width, mip_height, mip_img.into_raw(), PixelType::U8x4).unwrap();
+                alpha_mul_div.multiply_alpha_inplace(&mut mip).unwrap();
+
+                if premultiplied {
+                    mips.push(mip.buffer().to_vec());
+                }
+
+                mip
+            },
+            None => {
+                // Resize from the previous level
+                let mut mip = Image::new(mip_width, mip_height, PixelType::U8x4);
+                resizer.resize(&prev_mip, &mut mip, None).unwrap();
+
+                // Divide RGB by alpha, unless the mips are stored premultiplied
+                match premultiplied {
+                    true => mips.push(mip.buffer().to_vec()),
+                    false => {
+                        let mut straight_mip = Image::new(mip_width, mip_height, PixelType::U8x4);
+                        alpha_mul_div.divide_alpha(&mip, &mut straight_mip).unwrap();
+                        mips.push(straight_mip.into_vec());
+                    }
+                }
+
+                mip
+            }
+        };
 
-    // Pointer to src_img bytes
-    let mut src_img_buf = src_img.into_raw();
+        prev_mip = mip;
+    }
+
+    mips
+}
+
+// Generate every mip by resizing the full resolution source, returning their RGBA bytes
+// Slower on big textures, but avoids accumulating resize error on the smaller mips
+fn generate_mips_direct(
+    src_img: RgbaImage, mut custom_mips: HashMap<u32, RgbaImage>, mipmap_count: u32, premultiplied: bool
+) -> Vec<Vec<u8>> {
+    let (width, height) = src_img.dimensions();
 
     // Create source container for resize
-    let mut resize_src =
-        Image::from_slice_u8(width, height, src_img_buf.as_mut_slice(), PixelType::U8x4).unwrap();
+    let mut resize_src = Image::from_vec_u8(width, height, src_img.into_raw(), PixelType::U8x4).unwrap();
 
     // Multiply RGB by alpha (needed for resize algorithm)
     let alpha_mul_div = MulDiv::default();
     alpha_mul_div.multiply_alpha_inplace(&mut resize_src).unwrap();
 
-    let resize_src_arc = Arc::new(resize_src);
-    let alpha_mul_div_arc = Arc::new(alpha_mul_div);
-
     thread::scope(|s| {
         // Create all mipmaps
         let mut handles = Vec::new();
 
         for i in 0..mipmap_count {
-            let resize_src_clone = resize_src_arc.clone();
-            let alpha_mul_div_clone = alpha_mul_div_arc.clone();
+            let resize_src = &resize_src;
+            let alpha_mul_div = &alpha_mul_div;
+            let custom_mip = custom_mips.remove(&i);
 
             let handle = s.spawn(move || {
-                let power = 2_u32.pow(i);
-
-                // Get the mip's width and height
-                let mut mip_width = width / power;
-                let mut mip_height = height / power;
-
-                // Make sure they're not 0
-                if mip_width == 0 {
-                    mip_width = 1;
-                }
+                // Use the user-supplied mip as is
+                if let Some(mip_img) = custom_mip {
+                    if !premultiplied {
+                        return mip_img.into_raw();
+                    }
 
-                if mip_height == 0 {
-                    mip_height = 1;
+                    let (mip_width, mip_height) = mip_img.dimensions();
+                    let mut mip =
+                        Image::from_vec_u8(mip_width, mip_height, mip_img.into_raw(), PixelType::U8x4)
+                            .unwrap();
+                    alpha_mul_div.multiply_alpha_inplace(&mut mip).unwrap();
+                    return mip.into_vec();
                 }
 
                 // Create dest container for resize
-                let mut resize_dst = Image::new(mip_width, mip_height, resize_src_clone.pixel_type());
+                let (mip_width, mip_height) = mip_dimensions(width, height, i);
+                let mut resize_dst = Image::new(mip_width, mip_height, resize_src.pixel_type());
 
                 // Resize using Box filter
                 let mut resizer = Resizer::new();
-                resizer
-                    .resize(resize_src_clone.as_ref(), &mut resize_dst, None)
-                    .unwrap();
+                resizer.resize(resize_src, &mut resize_dst, None).unwrap();
 
-                // Divide RGB by alpha
-                alpha_mul_div_clone.divide_alpha_inplace(&mut resize_dst).unwrap();
+                // Divide RGB by alpha, unless the mips are stored premultiplied
+                if !premultiplied {
+                    alpha_mul_div.divide_alpha_inplace(&mut resize_dst).unwrap();
+                }
 
                 // Get resized bytes
-                let mut mip_img_bytes = resize_dst.buffer().to_vec();
-
-                // Get division remainder
-                let width_missing = 4 - mip_width % 4;
-                let height_missing = 4 - mip_height % 4;
-
-                // Add horizontal padding bytes
-                if width_missing != 4 {
-                    let new_mip_width = mip_width + width_missing;
-                    let stride = new_mip_width as usize * 4;
+                resize_dst.into_vec()
+            });
 
-                    // Iterate through rows
-                    for mut i in (0..stride * mip_height as usize).step_by(stride) {
-                        i += mip_width as usize * 4;
+            handles.push(handle);
+        }
 
-                        // Repeat the last pixel
-                        let mut last_pixel = vec![0_u8; width_missing as usize * 4];
+        // Join all threads
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    })
+}
 
-                        for j in 0..width_missing as usize {
-                            last_pixel[j * 4..j * 4 + 4].copy_from_slice(&mip_img_bytes[i - 4..i]);
-                        }
+// Multiply the color of already generated RGBA mips by their alpha
+fn premultiply_mips(mips: Vec<Vec<u8>>, width: u32, height: u32) -> Vec<Vec<u8>> {
+    let alpha_mul_div = MulDiv::default();
 
-                        mip_img_bytes.splice(i..i, last_pixel.iter().cloned());
-                    }
+    mips.into_iter()
+        .enumerate()
+        .map(|(level, mip_bytes)| {
+            let (mip_width, mip_height) = mip_dimensions(width, height, level as u32);
+            let mut mip = Image::from_vec_u8(mip_width, mip_height, mip_bytes, PixelType::U8x4).unwrap();
+            alpha_mul_div.multiply_alpha_inplace(&mut mip).unwrap();
+            mip.into_vec()
+        })
+        .collect()
+}
 
-                    mip_width = new_mip_width;
-                }
+// Compress the RGBA mips of a surface (2D texture or cubemap face) in parallel
+fn compress_mips(
+    mips: Vec<Vec<u8>>, width: u32, height: u32, format: TextureFormat, dest_z: u32
+) -> Vec<(Vec<u8>, BIMMipMap)> {
+    thread::scope(|s| {
+        // Compress all mipmaps
+        let mut handles = Vec::new();
 
-                // Add vertical padding bytes
-                if height_missing != 4 {
-                    // Get last row of pixels
-                    let mut last_row = vec![0_u8; mip_width as usize * 4];
-                    let size = mip_img_bytes.len();
-                    last_row.copy_from_slice(&mip_img_bytes[size - mip_width as usize * 4..size]);
-
-                    // Duplicate last row
-                    for i in 0..height_missing as usize {
-                        let insert_index = size + mip_width as usize * 4 * i;
-                        mip_img_bytes.splice(insert_index..insert_index, last_row.iter().cloned());
-                    }
+        for (i, mip_img_bytes) in mips.into_iter().enumerate() {
+            let handle = s.spawn(move || {
+                // Get the mip's width and height
+                let (mip_width, mip_height) = mip_dimensions(width, height, i as u32);
 
-                    mip_height += height_missing;
-                }
+                // Pad mip to a multiple of the block size
+                let (mip_img_bytes, padded_width, padded_height) = match format {
+                    // Uncompressed, no blocks to fill
+                    TextureFormat::FmtAlpha => (mip_img_bytes, mip_width, mip_height),
+                    _ => pad_to_block_size(mip_img_bytes, mip_width, mip_height)
+                };
 
                 // Compress to BCn format
-                let mip_bytes = compress_bcn(format, &mip_img_bytes, mip_width as usize, mip_height as usize);
+                let mip_bytes = compress_bcn(
+                    format,
+                    &mip_img_bytes,
+                    padded_width as usize,
+                    padded_height as usize
+                );
+
+                // Create mip header, with the unpadded size for non-power-of-two textures
+                // Power-of-two ones keep the padded size of their smallest mips, like the game's own
+                let (mip_width, mip_height) = match width.is_power_of_two() && height.is_power_of_two() {
+                    true => (padded_width, padded_height),
+                    false => (mip_width, mip_height)
+                };
 
-                // Create mip header
                 let bim_mip = BIMMipMap {
-                    mip_level: i as i64,
+                    mip_level: i as i32,
+                    mip_dest_z: dest_z as i32,
                     mip_pixel_width: mip_width,
                     mip_pixel_height: mip_height,
                     decompressed_size: mip_bytes.len() as u32,
@@ -270,201 +465,2023 @@ fn convert_to_bimage(
             handles.push(handle);
         }
 
-        let mut bim_mip_cumulative_size = 0_u32;
-
         // Join all threads
-        for handle in handles {
-            let mut mipmap = handle.join().unwrap();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    })
+}
 
-            // Append texture bytes
-            texture.append(&mut mipmap.0);
+// Check if transparent areas should be dilated, following the '$dilate' and '$nodilate' tokens
+// Decals and particles are dilated by default
+fn should_dilate(file_name: &str, material_kind: TextureMaterialKind) -> bool {
+    let dilated_kinds = [
+        TextureMaterialKind::TmkDecalalbedo,
+        TextureMaterialKind::TmkDecalnormal,
+        TextureMaterialKind::TmkDecalspecular,
+        TextureMaterialKind::TmkParticle
+    ];
+
+    match () {
+        // Premultiplied textures have no color in transparent areas
+        _ if file_name.contains("$premultiplied") => false,
+        _ if file_name.contains("$nodilate") => false,
+        _ if file_name.contains("$dilate") => true,
+        _ => dilated_kinds.contains(&material_kind)
+    }
+}
 
-            // Change cumulative size
-            let mut bim_mip = mipmap.1;
-            bim_mip.cumulative_size_streamdb = bim_mip_cumulative_size;
-            bim_mip_cumulative_size += bim_mip.compressed_size;
+// Spread color into transparent areas of every mip if they should be dilated,
+// so they don't bleed dark fringes when filtered or compressed
+fn dilate_mips(
+    mips: Vec<Vec<u8>>, width: u32, height: u32, file_name: &str, material_kind: TextureMaterialKind
+) -> Vec<Vec<u8>> {
+    if !should_dilate(file_name, material_kind) {
+        return mips;
+    }
 
-            // Append mip bytes
-            bim.extend_from_slice(&bim_mip.to_bytes());
-        }
-    });
+    mips.into_iter()
+        .enumerate()
+        .map(|(i, mut mip)| {
+            let (mip_width, mip_height) = mip_dimensions(width, height, i as u32);
+            dilate::dilate_transparent(&mut mip, mip_width, mip_height);
+            mip
+        })
+        .collect()
+}
 
-    // Change last bytes
-    let texture_len = texture.len();
+// Convert texture to bimage format used by the game
+fn convert_to_bimage(
+    src_img: RgbaImage, custom_mips: HashMap<u32, RgbaImage>, file_name: String, stripped_file_name: String,
+    format: TextureFormat, compress: bool
+) -> Result<Vec<u8>, String> {
+    // Get width and height
+    let (width, height) = src_img.dimensions();
 
-    if format == TextureFormat::FmtBc5 {
-        texture[texture_len - 16..].clone_from_slice(&[
-            0x87, 0x86, 0x49, 0x92, 0x24, 0x49, 0x92, 0x24, 0x86, 0x85, 0x49, 0x92, 0x24, 0x49, 0x92, 0x2
-        ]);
+    // Get mipmap count
+    let mipmap_count = get_mipmap_count(&file_name, width, height)?;
+
+    // Make sure custom mips fit in the mip chain
+    for (level, mip) in &custom_mips {
+        if *level >= mipmap_count {
+            return Err(format!(
+                "Custom mip {} exceeds the texture's mip count ({})",
+                level, mipmap_count
+            ));
+        }
+
+        let (expected_width, expected_height) = mip_dimensions(width, height, *level);
+
+        if mip.dimensions() != (expected_width, expected_height) {
+            return Err(format!(
+                "Custom mip {} is {}x{}, expected {}x{}",
+                level,
+                mip.width(),
+                mip.height(),
+                expected_width,
+                expected_height
+            ));
+        }
     }
-    else {
-        texture[texture_len - 4..].clone_from_slice(&[0_u8, 0_u8, 0_u8, 0_u8]);
+
+    // Get material kind
+    let material_kind = TextureMaterialKind::from_filename(file_name.clone(), stripped_file_name, format);
+
+    // Create BIM header
+    let bim_header = BIMHeader {
+        pixel_width: width,
+        pixel_height: height,
+        mip_count: mipmap_count,
+        texture_format: format as u32,
+        texture_material_kind: material_kind as u32,
+        // Padding around the frames of flipbook atlases
+        atlas_padding: get_atlas_padding(&file_name)? as i16,
+        ..Default::default()
+    };
+
+    // Store colors multiplied by alpha for shaders that expect it, instead of straight alpha
+    let premultiplied = file_name.contains("$premultiplied");
+
+    // Generate all mips, either from the previous level or straight from the source
+    let mips = match () {
+        _ if file_name.contains("$directmips") => {
+            generate_mips_direct(src_img, custom_mips, mipmap_count, premultiplied)
+        },
+        _ => generate_mips_cascade(src_img, custom_mips, mipmap_count, premultiplied)
+    };
+
+    let mips = dilate_mips(mips, width, height, &file_name, material_kind);
+    let bim_mips = compress_mips(mips, width, height, format, 0);
+
+    build_bimage(bim_header, bim_mips, true, compress)
+}
+
+// Split a horizontal (4x3) or vertical (3x4) cross into the six cubemap faces, ordered +X, -X, +Y, -Y, +Z, -Z
+//
+//      horizontal           vertical
+//         +Y                   +Y
+//     -X  +Z  +X  -Z       -X  +Z  +X
+//         -Y                   -Y
+//                              -Z (upside down)
+fn split_cube_cross(img: &RgbaImage) -> Result<Vec<RgbaImage>, String> {
+    let (width, height) = img.dimensions();
+
+    // Face size and cell of every face in the cross
+    let (face_size, cells) = match () {
+        _ if width * 3 == height * 4 && width % 4 == 0 => {
+            (width / 4, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)])
+        },
+        _ if width * 4 == height * 3 && width % 3 == 0 => {
+            (width / 3, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)])
+        },
+        _ => {
+            return Err(format!(
+                "{}x{} is neither a horizontal (4x3) nor a vertical (3x4) cubemap cross",
+                width, height
+            ))
+        },
+    };
+
+    let mut faces: Vec<RgbaImage> = cells
+        .iter()
+        .map(|(column, row)| {
+            image::imageops::crop_imm(img, column * face_size, row * face_size, face_size, face_size)
+                .to_image()
+        })
+        .collect();
+
+    // The vertical cross has -Z upside down
+    if height > width {
+        image::imageops::rotate180_in_place(&mut faces[5]);
     }
 
-    // Add dds bytes to bim
-    bim.append(&mut texture);
+    Ok(faces)
+}
 
-    // Compress bim texture with kraken
-    if compress {
-        kraken_compress(&mut bim)
+// Convert the six faces of a cubemap, ordered +X, -X, +Y, -Y, +Z, -Z, to bimage format
+fn convert_cubemap_to_bimage(
+    faces: Vec<RgbaImage>, file_name: String, stripped_file_name: String, format: TextureFormat,
+    compress: bool
+) -> Result<Vec<u8>, String> {
+    if faces.len() != 6 {
+        return Err(format!("Cubemaps need 6 faces, got {}", faces.len()));
     }
-    else {
-        Ok(bim)
+
+    // Faces must be square and all the same size
+    let size = faces[0].width();
+
+    for (face, img) in faces.iter().enumerate() {
+        if img.dimensions() != (size, size) {
+            return Err(format!(
+                "Face {} is {}x{}, expected {}x{}",
+                face,
+                img.width(),
+                img.height(),
+                size,
+                size
+            ));
+        }
+    }
+
+    // Get mipmap count, for every face
+    let mipmap_count = get_mipmap_count(&file_name, size, size)?;
+
+    // Get material kind
+    let material_kind = TextureMaterialKind::from_filename(file_name.clone(), stripped_file_name, format);
+
+    // Create BIM header
+    let bim_header = BIMHeader {
+        texture_type: TextureType::TtCubic as u32,
+        pixel_width: size,
+        pixel_height: size,
+        mip_count: mipmap_count,
+        bool_is_environment_map: 1,
+        texture_format: format as u32,
+        texture_material_kind: material_kind as u32,
+        ..Default::default()
+    };
+
+    // Store colors multiplied by alpha for shaders that expect it, instead of straight alpha
+    let premultiplied = file_name.contains("$premultiplied");
+
+    // Generate all mips, either convolved for reflections or by resizing each face
+    let face_mips: Vec<Vec<Vec<u8>>> = match () {
+        _ if file_name.contains("$prefilter") => {
+            let sample_count = match utils::parse_token(&file_name, "samples")? {
+                Some(0) => return Err("Sample count must be greater than 0".into()),
+                Some(sample_count) => sample_count,
+                None => 128
+            };
+
+            let face_mips =
+                cubemap::prefilter_cube_faces(&faces, mipmap_count, sample_count, format.is_srgb());
+
+            match premultiplied {
+                true => face_mips
+                    .into_iter()
+                    .map(|mips| premultiply_mips(mips, size, size))
+                    .collect(),
+                false => face_mips
+            }
+        },
+        _ if file_name.contains("$directmips") => faces
+            .into_iter()
+            .map(|img| generate_mips_direct(img, HashMap::new(), mipmap_count, premultiplied))
+            .collect(),
+        _ => faces
+            .into_iter()
+            .map(|img| generate_mips_cascade(img, HashMap::new(), mipmap_count, premultiplied))
+            .collect()
+    };
+
+    // Faces are stored one after another, each with its whole mip chain
+    let mut bim_mips = Vec::with_capacity(6 * mipmap_count as usize);
+
+    for (face, mips) in face_mips.into_iter().enumerate() {
+        let mips = dilate_mips(mips, size, size, &file_name, material_kind);
+        bim_mips.extend(compress_mips(mips, size, size, format, face as u32));
     }
+
+    build_bimage(bim_header, bim_mips, true, compress)
 }
 
-// Load textures, convert them to bim, and compress them
-fn handle_textures(paths: Vec<String>) -> u32 {
-    let paths_len = paths.len() as u32;
+// Convert the slices of an array or volume texture to bimage format
+fn convert_slices_to_bimage(
+    slices: Vec<RgbaImage>, file_name: String, stripped_file_name: String, format: TextureFormat,
+    compress: bool
+) -> Result<Vec<u8>, String> {
+    // Slices must all be the same size
+    let (width, height) = slices[0].dimensions();
+
+    for (slice, img) in slices.iter().enumerate() {
+        if img.dimensions() != (width, height) {
+            return Err(format!(
+                "Slice {} is {}x{}, expected {}x{}",
+                slice,
+                img.width(),
+                img.height(),
+                width,
+                height
+            ));
+        }
+    }
 
-    // Thread handles
-    let mut handles = Vec::new();
+    // Volume mips would have to be downsampled in depth too, so volumes only keep their top level
+    let (texture_type, mipmap_count) = match file_name.contains("$volume") {
+        true if ["mips", "minmip"]
+            .iter()
+            .any(|key| utils::get_token_value(&file_name, key).is_some()) =>
+        {
+            return Err(
+                "Volume textures have no mips, '$mips' and '$minmip' can't be used with '$volume'".into()
+            )
+        },
+        true => (TextureType::Tt3d, 1),
+        false => (TextureType::Tt2d, get_mipmap_count(&file_name, width, height)?)
+    };
 
-    // Count successful conversions
-    let counter = Arc::new(Mutex::new(0));
+    // Get material kind
+    let material_kind = TextureMaterialKind::from_filename(file_name.clone(), stripped_file_name, format);
+
+    // Create BIM header
+    let bim_header = BIMHeader {
+        texture_type: texture_type as u32,
+        pixel_width: width,
+        pixel_height: height,
+        depth: slices.len() as u32,
+        mip_count: mipmap_count,
+        texture_format: format as u32,
+        texture_material_kind: material_kind as u32,
+        ..Default::default()
+    };
 
-    // Iterate through args
-    for path in paths {
-        let counter = counter.clone();
+    // Store colors multiplied by alpha for shaders that expect it, instead of straight alpha
+    let premultiplied = file_name.contains("$premultiplied");
 
-        let handle = thread::spawn(move || {
-            let mut output = String::default();
-            writeln!(&mut output).unwrap();
+    // Slices are stored one after another, each with its whole mip chain
+    let mut bim_mips = Vec::with_capacity(slices.len() * mipmap_count as usize);
 
-            // Get texture's format and stripped filename
-            let file_path = Path::new(&path);
-            let file_name = file_path.file_name().unwrap().to_str().unwrap().to_owned();
-            let stripped_file_name = file_name
-                .split('$')
-                .next()
-                .unwrap()
-                .split('.')
-                .next()
-                .unwrap()
-                .to_owned();
+    for (slice, img) in slices.into_iter().enumerate() {
+        let mips = match () {
+            _ if file_name.contains("$directmips") => {
+                generate_mips_direct(img, HashMap::new(), mipmap_count, premultiplied)
+            },
+            _ => generate_mips_cascade(img, HashMap::new(), mipmap_count, premultiplied)
+        };
 
-            writeln!(&mut output, "Converting '{}'...", file_name).unwrap();
+        let mips = dilate_mips(mips, width, height, &file_name, material_kind);
+        bim_mips.extend(compress_mips(mips, width, height, format, slice as u32));
+    }
 
-            // Check if given path exists and is a file
-            if !file_path.is_file() {
-                writeln!(&mut output, "ERROR: '{}' was not found.", path).unwrap();
-                return output;
-            }
+    build_bimage(bim_header, bim_mips, true, compress)
+}
 
-            // Get target format
-            let format = match () {
-                _ if file_name.contains("$bc7") => TextureFormat::FmtBc7,
-                _ if file_name.contains("$bc5") => TextureFormat::FmtBc5,
-                _ if file_name.contains("$bc4") => TextureFormat::FmtBc4,
-                _ if file_name.contains("$bc3") => TextureFormat::FmtBc3,
-                _ if file_name.contains("$alpha") => TextureFormat::FmtAlpha,
-                _ if stripped_file_name.ends_with("_n") => TextureFormat::FmtBc5,
-                _ if stripped_file_name.ends_with("_Normal") => TextureFormat::FmtBc5,
-                _ => TextureFormat::FmtBc1Srgb
-            };
+// Wrap already compressed DDS texture into bimage format, without re-encoding it
+fn convert_dds_to_bimage(
+    dds: DdsTexture, file_name: String, stripped_file_name: String, compress: bool, output: &mut String
+) -> Result<Vec<u8>, String> {
+    // Mips and format come from the DDS file
+    let ignored_tokens: Vec<&str> = [
+        "$nomips",
+        "$mips=",
+        "$minmip=",
+        "$bc7",
+        "$bc5",
+        "$bc4",
+        "$bc3",
+        "$alpha",
+        "$premultiplied"
+    ]
+    .into_iter()
+    .filter(|token| file_name.contains(token))
+    .map(|token| token.trim_end_matches('='))
+    .collect();
+
+    if !ignored_tokens.is_empty() {
+        writeln!(
+            output,
+            "WARNING: Ignoring {}, DDS data is wrapped as is, with its own mips and format.",
+            ignored_tokens.join(", ")
+        )
+        .unwrap();
+    }
 
-            // Load image
-            let mut src_reader = match ImageReader::open(file_path) {
-                Ok(reader) => reader,
-                Err(e) => {
-                    writeln!(&mut output, "ERROR: Failed to load '{}': {}", path, e).unwrap();
-                    return output;
-                }
-            };
+    // Legacy DXT1 files are treated like other albedo textures, which are sRGB
+    let format = match dds.format {
+        TextureFormat::FmtBc1
+            if dds.legacy_header
+                && TextureMaterialKind::from_filename(
+                    file_name.clone(),
+                    stripped_file_name.clone(),
+                    TextureFormat::FmtBc1Srgb
+                ) == TextureMaterialKind::TmkAlbedo =>
+        {
+            TextureFormat::FmtBc1Srgb
+        },
+        format => format
+    };
 
-            src_reader.set_format(ImageFormat::Png);
+    // Create BIM header
+    let bim_header = BIMHeader {
+        pixel_width: dds.width,
+        pixel_height: dds.height,
+        mip_count: dds.mip_count,
+        texture_format: format as u32,
+        texture_material_kind: TextureMaterialKind::from_filename(file_name, stripped_file_name, format)
+            as u32,
+        ..Default::default()
+    };
 
-            let src_img = match src_reader.decode() {
-                Ok(img) => img.into_rgba8(),
-                Err(e) => {
-                    writeln!(&mut output, "ERROR: Failed to load '{}': {}", path, e).unwrap();
-                    return output;
-                }
-            };
+    // Split the DDS data into mips
+    let mut bim_mips = Vec::with_capacity(dds.mip_count as usize);
+    let mut offset = 0;
 
-            // Check if image should be compressed
-            let compress = env::var("AUTOHECKIN_SKIP_COMPRESSION").is_err();
+    for level in 0..dds.mip_count {
+        let (mip_width, mip_height) = mip_dimensions(dds.width, dds.height, level);
+        let mip_size = dds.format.mip_size(mip_width, mip_height).unwrap();
 
-            // Convert image to bimage format
-            let bim_bytes =
-                match convert_to_bimage(src_img, file_name.clone(), stripped_file_name, format, compress) {
-                    Ok(vec) => vec,
-                    Err(e) => {
-                        writeln!(&mut output, "ERROR: Failed to convert '{}' to DDS: {}", path, e).unwrap();
-                        return output;
-                    }
-                };
+        let mip_bytes = match dds.data.get(offset..offset + mip_size) {
+            Some(mip_bytes) => mip_bytes.to_vec(),
+            None => return Err("DDS file is truncated".into())
+        };
 
-            // Get output filename
-            let new_extension: &str;
+        offset += mip_size;
 
-            if !file_name.contains('$') {
-                if file_path.file_stem().unwrap().to_str().unwrap().ends_with(".png") {
-                    new_extension = "png";
-                }
-                else {
-                    new_extension = "tga"
-                }
-            }
-            else {
-                let curr_extension = Path::new(&file_name).extension().unwrap().to_str().unwrap();
+        // Create mip header
+        let bim_mip = BIMMipMap {
+            mip_level: level as i32,
+            mip_pixel_width: mip_width,
+            mip_pixel_height: mip_height,
+            decompressed_size: mip_size as u32,
+            compressed_size: mip_size as u32,
+            ..Default::default()
+        };
 
-                if curr_extension.contains('$') {
-                    new_extension = curr_extension;
-                }
-                else {
-                    new_extension = "";
-                }
-            }
+        bim_mips.push((mip_bytes, bim_mip));
+    }
 
-            // Prevent overwriting
-            let new_file_path = if file_path.with_extension(new_extension).exists() {
-                // Append -i, with the least possible number
-                let trunc_path = file_path.with_extension("").to_str().unwrap().to_owned();
-                let mut i = 2_u32;
+    // Keep the user's blocks untouched
+    build_bimage(bim_header, bim_mips, false, compress)
+}
 
-                let dot = match new_extension {
-                    "" => "",
-                    _ => "."
-                };
+// Read bimage file, decompressing it if needed. Returns None if the file isn't a bimage
+fn read_bimage(path: &Path) -> Option<Result<Vec<u8>, String>> {
+    let bytes = fs::read(path).ok()?;
 
-                while Path::new(&(trunc_path.clone() + "-" + &i.to_string() + dot + new_extension)).exists() {
-                    i += 1;
-                }
+    match () {
+        _ if bytes.starts_with(&KRAKEN_MAGIC) => Some(kraken_decompress(&bytes)),
+        _ if BIMHeader::from_bytes(&bytes).is_some() => Some(Ok(bytes)),
+        _ => None
+    }
+}
 
-                PathBuf::from(&(trunc_path + "-" + &i.to_string() + dot + new_extension))
-            }
-            else {
-                file_path.with_extension(new_extension)
-            };
+// Data of every mip of a bimage, indexed by cubemap face or slice, then by level
+type BimageMips<'a> = Vec<Vec<&'a [u8]>>;
 
-            // Get filename
-            let new_file_name = new_file_path.file_name().unwrap().to_str().unwrap();
+// Get bimage's header, format and the data of every mip
+fn read_bimage_mips(bim: &[u8]) -> Result<(BIMHeader, TextureFormat, BimageMips<'_>), String> {
+    let bim_header = BIMHeader::from_bytes(bim).ok_or("Invalid bimage header")?;
 
-            // Adquire lock
-            let mut counter = counter.lock().unwrap();
+    // Cubemap faces, or slices of volumes and texture arrays
+    let image_count = match bim_header.texture_type {
+        texture_type if texture_type == TextureType::TtCubic as u32 => 6,
+        texture_type if texture_type == TextureType::Tt3d as u32 && bim_header.mip_count > 1 => {
+            return Err("Volume textures with mips are not supported".into())
+        },
+        texture_type
+            if texture_type == TextureType::Tt2d as u32 || texture_type == TextureType::Tt3d as u32 =>
+        {
+            cmp::max(bim_header.depth, 1) as usize
+        },
+        texture_type => return Err(format!("Unsupported texture type {}", texture_type))
+    };
 
-            // Write output file
-            let mut output_file = match File::create(new_file_path.to_str().unwrap()) {
-                Ok(f) => f,
-                Err(e) => {
-                    writeln!(&mut output, "ERROR: Failed to create output file: {}", e).unwrap();
-                    return output;
-                }
-            };
+    let format = match TextureFormat::from_u32(bim_header.texture_format) {
+        Some(format) if format.mip_size(1, 1).is_some() => format,
+        _ => {
+            return Err(format!(
+                "Unsupported texture format {}",
+                bim_header.texture_format
+            ))
+        },
+    };
 
-            match output_file.write_all(&bim_bytes) {
-                Ok(_) => (),
-                Err(e) => {
-                    writeln!(&mut output, "ERROR: Failed to write to output file: {}", e).unwrap();
+    // Every face or slice has its whole mip chain
+    let mip_count = bim_header.mip_count as usize;
+    let entry_count = image_count * mip_count;
+    let texture_start = BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * entry_count;
+    let mut images = vec![vec![None; mip_count]; image_count];
+
+    for entry in 0..entry_count {
+        let entry_start = BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * entry;
+        let bim_mip = match bim.get(entry_start..entry_start + BIM_MIP_MAP_SIZE) {
+            Some(entry) => BIMMipMap::from_bytes(entry),
+            None => return Err("Bimage is truncated".into())
+        };
+
+        if bim_mip.flag_is_compressed != 0 {
+            return Err("Compressed mips are not supported".into());
+        }
+
+        let (level, image) = (bim_mip.mip_level as usize, bim_mip.mip_dest_z as usize);
+
+        if level >= mip_count || image >= image_count {
+            return Err(format!("Mip entry {} is out of range", entry));
+        }
+
+        let mip_size = format
+            .mip_size(bim_mip.mip_pixel_width, bim_mip.mip_pixel_height)
+            .unwrap();
+
+        if bim_mip.decompressed_size as usize != mip_size {
+            return Err(format!(
+                "Mip {} is {} bytes, expected {} for {}x{}",
+                level, bim_mip.decompressed_size, mip_size, bim_mip.mip_pixel_width, bim_mip.mip_pixel_height
+            ));
+        }
+        let mip_start = texture_start + bim_mip.cumulative_size_streamdb as usize;
+
+        match bim.get(mip_start..mip_start + mip_size) {
+            Some(mip_bytes) => images[image][level] = Some(mip_bytes),
+            None => return Err(format!("Mip {} is missing, the texture may be streamed", level))
+        }
+    }
+
+    let images = images
+        .into_iter()
+        .map(|mips| mips.into_iter().collect::<Option<Vec<&[u8]>>>())
+        .collect::<Option<BimageMips>>()
+        .ok_or("Bimage has duplicate mip entries")?;
+
+    Ok((bim_header, format, images))
+}
+
+// Export bimage to DDS, keeping its format and mips as they are
+fn convert_bimage_to_dds(bim: &[u8]) -> Result<Vec<u8>, String> {
+    let (bim_header, format, images) = read_bimage_mips(bim)?;
+
+    if DxgiFormat::from_texture_format(format).is_none() {
+        return Err(format!(
+            "Unsupported texture format {}",
+            bim_header.texture_format
+        ));
+    }
+
+    let texture_type = match bim_header.texture_type {
+        texture_type if texture_type == TextureType::TtCubic as u32 => TextureType::TtCubic,
+        texture_type if texture_type == TextureType::Tt3d as u32 => TextureType::Tt3d,
+        _ => TextureType::Tt2d
+    };
+
+    // Volume slices all go in their only mip, everything else is stored face or slice first like bimages
+    let data = match texture_type {
+        TextureType::Tt3d => images
+            .into_iter()
+            .map(|mips| mips[0])
+            .collect::<Vec<&[u8]>>()
+            .concat(),
+        _ => images.concat().concat()
+    };
+
+    Ok(DdsTexture {
+        width: bim_header.pixel_width,
+        height: bim_header.pixel_height,
+        depth: cmp::max(bim_header.depth, 1),
+        mip_count: bim_header.mip_count,
+        texture_type,
+        format,
+        legacy_header: false,
+        data
+    }
+    .to_bytes())
+}
+
+// Export bimage to KTX2, keeping its format and mips as they are
+fn convert_bimage_to_ktx2(bim: &[u8]) -> Result<Vec<u8>, String> {
+    let (bim_header, format, images) = read_bimage_mips(bim)?;
+
+    if VkFormat::from_texture_format(format).is_none() {
+        return Err(format!(
+            "Unsupported texture format {}",
+            bim_header.texture_format
+        ));
+    }
+
+    let (depth, layers, faces) = match bim_header.texture_type {
+        texture_type if texture_type == TextureType::TtCubic as u32 => (0, 0, 6),
+        texture_type if texture_type == TextureType::Tt3d as u32 => (images.len() as u32, 0, 1),
+        _ if images.len() > 1 => (0, images.len() as u32, 1),
+        _ => (0, 0, 1)
+    };
+
+    // Every level holds that mip of all the faces or slices
+    let levels = (0..bim_header.mip_count as usize)
+        .map(|level| {
+            images
+                .iter()
+                .map(|mips| mips[level])
+                .collect::<Vec<&[u8]>>()
+                .concat()
+        })
+        .collect();
+
+    Ok(Ktx2Texture {
+        width: bim_header.pixel_width,
+        height: bim_header.pixel_height,
+        depth,
+        layers,
+        faces,
+        format,
+        levels
+    }
+    .to_bytes())
+}
+
+// Assemble bimage from its header and already compressed mips
+// The end of the texture is marked like the game does when mark_end is set
+fn build_bimage(
+    bim_header: BIMHeader, bim_mips: Vec<(Vec<u8>, BIMMipMap)>, mark_end: bool, compress: bool
+) -> Result<Vec<u8>, String> {
+    // BIM bytes
+    let texture_size: usize = bim_mips.iter().map(|(mip_bytes, _)| mip_bytes.len()).sum();
+    let mut texture = Vec::with_capacity(texture_size);
+    let mut bim = Vec::with_capacity(BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * bim_mips.len() + texture_size);
+
+    // Append BIM header
+    bim.extend_from_slice(&bim_header.to_bytes());
+
+    let mut bim_mip_cumulative_size = 0_u32;
+
+    for (mut mip_bytes, mut bim_mip) in bim_mips {
+        // Append texture bytes
+        texture.append(&mut mip_bytes);
+
+        // Change cumulative size
+        bim_mip.cumulative_size_streamdb = bim_mip_cumulative_size;
+        bim_mip_cumulative_size += bim_mip.compressed_size;
+
+        // Append mip bytes
+        bim.extend_from_slice(&bim_mip.to_bytes());
+    }
+
+    // Change last bytes of block compressed textures
+    // Uncompressed alpha mips aren't padded, so the last bytes are real pixels
+    let texture_len = texture.len();
+    let trailer: &[u8] = match bim_header.texture_format {
+        _ if !mark_end => &[],
+        format if format == TextureFormat::FmtAlpha as u32 => &[],
+        format if format == TextureFormat::FmtBc5 as u32 => &[
+            0x87, 0x86, 0x49, 0x92, 0x24, 0x49, 0x92, 0x24, 0x86, 0x85, 0x49, 0x92, 0x24, 0x49, 0x92, 0x2
+        ],
+        _ => &[0_u8, 0_u8, 0_u8, 0_u8]
+    };
+
+    if !trailer.is_empty() && texture_len >= trailer.len() {
+        texture[texture_len - trailer.len()..].clone_from_slice(trailer);
+    }
+
+    // Add dds bytes to bim
+    bim.append(&mut texture);
+
+    // Compress bim texture with kraken
+    if compress {
+        kraken_compress(&mut bim)
+    }
+    else {
+        Ok(bim)
+    }
+}
+
+// Load image from the given path
+fn load_image(path: &Path, output: &mut String) -> Result<RgbaImage, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+
+    // Detect format from the file contents, since the extension is usually part of the game's name
+    // TGA has no magic bytes, so assume it when nothing else matches
+    let format = image::guess_format(&bytes).unwrap_or(ImageFormat::Tga);
+
+    let img = image::load_from_memory_with_format(&bytes, format).map_err(|e| e.to_string())?;
+
+    // Textures are stored in 8 bits, so HDR values can't be kept
+    let clipped = match &img {
+        DynamicImage::ImageRgb32F(img) => img.as_raw().iter().any(|value| *value > 1.0),
+        DynamicImage::ImageRgba32F(img) => img.as_raw().iter().any(|value| *value > 1.0),
+        _ => false
+    };
+
+    if clipped {
+        writeln!(
+            output,
+            "WARNING: '{}' has HDR values above 1.0, they are clipped to fit in 8 bits.",
+            path.display()
+        )
+        .unwrap();
+    }
+
+    Ok(img.into_rgba8())
+}
+
+// Resize RGBA image to the given size
+fn resize_image(img: RgbaImage, new_width: u32, new_height: u32) -> RgbaImage {
+    let (width, height) = img.dimensions();
+
+    // Multiply RGB by alpha (needed for resize algorithm)
+    let alpha_mul_div = MulDiv::default();
+    let mut resize_src = Image::from_vec_u8(width, height, img.into_raw(), PixelType::U8x4).unwrap();
+    alpha_mul_div.multiply_alpha_inplace(&mut resize_src).unwrap();
+
+    let mut resize_dst = Image::new(new_width, new_height, PixelType::U8x4);
+    Resizer::new().resize(&resize_src, &mut resize_dst, None).unwrap();
+
+    // Divide RGB by alpha
+    alpha_mul_div.divide_alpha_inplace(&mut resize_dst).unwrap();
+
+    RgbaImage::from_raw(new_width, new_height, resize_dst.into_vec()).unwrap()
+}
+
+// Handle non-power-of-two textures according to the '$npot=pad|pow2|crop|reject' token
+// Returns the resulting image and a warning describing what was done, if anything
+fn apply_size_policy(img: RgbaImage, file_name: &str) -> Result<(RgbaImage, Option<String>), String> {
+    let (width, height) = img.dimensions();
+
+    if width.is_power_of_two() && height.is_power_of_two() {
+        return Ok((img, None));
+    }
+
+    match utils::get_token_value(file_name, "npot").unwrap_or("pad") {
+        "pad" => {
+            let warning = format!(
+                "Texture size {}x{} is not a power of two, mips will be padded to a multiple of 4 by \
+                 repeating their edge pixels.",
+                width, height
+            );
+
+            Ok((img, Some(warning)))
+        },
+        "pow2" => {
+            // Round each dimension to the nearest power of two
+            let nearest_pow2 = |size: u32| {
+                let upper = size.next_power_of_two();
+                let lower = upper >> 1;
+
+                match () {
+                    _ if lower > 0 && size - lower < upper - size => lower,
+                    _ => upper
+                }
+            };
+
+            let (new_width, new_height) = (nearest_pow2(width), nearest_pow2(height));
+            let warning = format!(
+                "Texture size {}x{} is not a power of two, resized to {}x{}.",
+                width, height, new_width, new_height
+            );
+
+            Ok((resize_image(img, new_width, new_height), Some(warning)))
+        },
+        "crop" => {
+            // Keep the top-left corner, so pixel coordinates are unchanged
+            let prev_pow2 = |size: u32| 1 << size.ilog2();
+            let (new_width, new_height) = (prev_pow2(width), prev_pow2(height));
+            let warning = format!(
+                "Texture size {}x{} is not a power of two, cropped to {}x{}.",
+                width, height, new_width, new_height
+            );
+
+            let cropped = image::imageops::crop_imm(&img, 0, 0, new_width, new_height).to_image();
+            Ok((cropped, Some(warning)))
+        },
+        "reject" => Err(format!("Texture size {}x{} is not a power of two", width, height)),
+        policy => Err(format!("Unknown non-power-of-two policy '{}'", policy))
+    }
+}
+
+// Apply the non-power-of-two policy to every face or slice of a texture, warning only once
+fn apply_size_policy_to_all(
+    imgs: Vec<RgbaImage>, file_name: &str, output: &mut String
+) -> Result<Vec<RgbaImage>, String> {
+    let mut warning = None;
+
+    let imgs = imgs
+        .into_iter()
+        .map(|img| {
+            let (img, img_warning) = apply_size_policy(img, file_name)?;
+            warning = warning.take().or(img_warning);
+            Ok(img)
+        })
+        .collect::<Result<Vec<RgbaImage>, String>>()?;
+
+    if let Some(warning) = warning {
+        writeln!(output, "WARNING: {}", warning).unwrap();
+    }
+
+    Ok(imgs)
+}
+
+// Resize or crop custom mips like their texture was by the non-power-of-two policy
+// Mips that weren't the right size to begin with are left for the mip chain check to report
+fn fit_custom_mips(
+    custom_mips: HashMap<u32, RgbaImage>, file_name: &str, src_size: (u32, u32), new_size: (u32, u32)
+) -> HashMap<u32, RgbaImage> {
+    if src_size == new_size {
+        return custom_mips;
+    }
+
+    custom_mips
+        .into_iter()
+        .map(|(level, mip)| {
+            let (src_width, src_height) = mip_dimensions(src_size.0, src_size.1, level);
+            let (new_width, new_height) = mip_dimensions(new_size.0, new_size.1, level);
+
+            let mip = match () {
+                _ if mip.dimensions() != (src_width, src_height) => mip,
+                _ if utils::get_token_value(file_name, "npot") == Some("crop") => {
+                    image::imageops::crop_imm(&mip, 0, 0, new_width, new_height).to_image()
+                },
+                _ => resize_image(mip, new_width, new_height)
+            };
+
+            (level, mip)
+        })
+        .collect()
+}
+
+// Generate a distance field from a high resolution glyph image, following the '$spread' and '$sdfsize' tokens
+fn generate_sdf_image(img: RgbaImage, file_name: &str, output: &mut String) -> Result<RgbaImage, String> {
+    let (width, height) = img.dimensions();
+
+    // Default to an eighth of the source's size, keeping its aspect ratio
+    let new_width = match utils::parse_token::<u32>(file_name, "sdfsize")? {
+        Some(0) => return Err("SDF size must be greater than 0".into()),
+        Some(new_width) => new_width,
+        None => (width / 8).max(1)
+    };
+
+    let new_height = ((height as f64 * new_width as f64 / width as f64).round() as u32).max(1);
+
+    let spread = match utils::parse_token::<f32>(file_name, "spread")? {
+        Some(spread) if spread <= 0.0 => return Err("SDF spread must be greater than 0".into()),
+        Some(spread) => spread,
+        None => 4.0
+    };
+
+    writeln!(
+        output,
+        "Generating {}x{} distance field with a spread of {} pixels.",
+        new_width, new_height, spread
+    )
+    .unwrap();
+
+    Ok(sdf::generate_sdf(&img, spread, new_width, new_height))
+}
+
+// Load the user-supplied mips next to the given texture (e.g. 'name$mip1.png')
+fn load_custom_mips(path: &Path, output: &mut String) -> Result<HashMap<u32, RgbaImage>, String> {
+    let mut custom_mips = HashMap::new();
+
+    // Mip 0 is the texture itself
+    for level in 1..32 {
+        let mip_path = match utils::custom_mip_path(path, level) {
+            Some(mip_path) => mip_path,
+            None => break
+        };
+
+        if !mip_path.is_file() {
+            continue;
+        }
+
+        match load_image(&mip_path, output) {
+            Ok(img) => {
+                custom_mips.insert(level, img);
+            },
+            Err(e) => return Err(format!("Failed to load '{}': {}", mip_path.display(), e))
+        }
+    }
+
+    Ok(custom_mips)
+}
+
+// Fill single channel formats from the source channel given by the '$channel=r|g|b|a|luminance' token,
+// or else from alpha if the first image has any transparency and from luminance if it doesn't
+// Alpha textures read alpha and BC4 reads the color channels, so alpha is left opaque for BC4 to resize cleanly
+fn apply_source_channel<'a>(
+    mut imgs: impl Iterator<Item = &'a mut RgbaImage>, file_name: &str, format: TextureFormat,
+    output: &mut String
+) -> Result<(), String> {
+    let opaque = match format {
+        TextureFormat::FmtAlpha => false,
+        TextureFormat::FmtBc4 => true,
+        _ => return Ok(())
+    };
+
+    let first_img = match imgs.next() {
+        Some(img) => img,
+        None => return Ok(())
+    };
+
+    // BC4 keeps its red channel by default, like before the channel could be picked
+    let channel = match (utils::parse_token::<Channel>(file_name, "channel")?, format) {
+        (Some(channel), _) => channel,
+        (None, TextureFormat::FmtBc4) => Channel::Red,
+        (None, _) => Channel::detect(first_img)
+    };
+
+    writeln!(output, "Reading the texture from the {} channel.", channel.name()).unwrap();
+    channels::fill_from_channel(first_img, channel, opaque);
+
+    for img in imgs {
+        channels::fill_from_channel(img, channel, opaque);
+    }
+
+    Ok(())
+}
+
+// Prepare BC5 and normal textures following the '$flipy' and '$renormalize' tokens
+// Vectors are re-encoded anyway, so X and Y land in the red and green channels BC5 keeps
+fn apply_normal_preprocessing<'a>(
+    imgs: impl Iterator<Item = &'a mut RgbaImage>, file_name: &str, stripped_file_name: &str,
+    format: TextureFormat, output: &mut String
+) {
+    let material_kind =
+        TextureMaterialKind::from_filename(file_name.to_owned(), stripped_file_name.to_owned(), format);
+
+    if format != TextureFormat::FmtBc5 && material_kind != TextureMaterialKind::TmkNormal {
+        return;
+    }
+
+    let flip_y = file_name.contains("$flipy");
+    let renormalize = file_name.contains("$renormalize");
+
+    if flip_y {
+        writeln!(output, "Flipping the normal map's Y channel.").unwrap();
+    }
+
+    if renormalize {
+        writeln!(output, "Renormalizing the normal map's vectors.").unwrap();
+    }
+
+    for img in imgs {
+        normals::preprocess_normal_map(img, flip_y, renormalize, format == TextureFormat::FmtBc5);
+    }
+}
+
+// Get target format from the filename
+fn get_target_format(file_name: &str, stripped_file_name: &str) -> TextureFormat {
+    match () {
+        _ if file_name.contains("$bc7") => TextureFormat::FmtBc7,
+        _ if file_name.contains("$bc5") => TextureFormat::FmtBc5,
+        _ if file_name.contains("$bc4") => TextureFormat::FmtBc4,
+        _ if file_name.contains("$bc3") => TextureFormat::FmtBc3,
+        _ if file_name.contains("$alpha") => TextureFormat::FmtAlpha,
+        _ if utils::has_token(file_name, "sdf") => TextureFormat::FmtAlpha,
+        _ if utils::is_font_file(file_name) => TextureFormat::FmtAlpha,
+        _ if stripped_file_name.ends_with("_n") => TextureFormat::FmtBc5,
+        _ if stripped_file_name.ends_with("_Normal") => TextureFormat::FmtBc5,
+        _ => TextureFormat::FmtBc1Srgb
+    }
+}
+
+// Load image, convert it to bimage format and compress it
+fn convert_image_file(
+    file_path: &Path, file_name: &str, stripped_file_name: String, compress: bool, output: &mut String
+) -> Result<Vec<u8>, String> {
+    let path = file_path.display();
+
+    // Load image
+    let src_img = match load_image(file_path, output) {
+        Ok(img) => img,
+        Err(e) => return Err(format!("Failed to load '{}': {}", path, e))
+    };
+
+    convert_rgba_image(
+        src_img,
+        file_path,
+        file_name,
+        stripped_file_name,
+        compress,
+        output
+    )
+}
+
+// Convert an already loaded image to bimage format and compress it
+fn convert_rgba_image(
+    src_img: RgbaImage, file_path: &Path, file_name: &str, stripped_file_name: String, compress: bool,
+    output: &mut String
+) -> Result<Vec<u8>, String> {
+    let path = file_path.display();
+
+    // Get target format
+    let format = get_target_format(file_name, &stripped_file_name);
+
+    // Turn high resolution glyphs into a distance field
+    let src_img = match utils::has_token(file_name, "sdf") {
+        true => match generate_sdf_image(src_img, file_name, output) {
+            Ok(img) => img,
+            Err(e) => return Err(format!("Failed to convert '{}': {}", path, e))
+        },
+        false => src_img
+    };
+
+    // Handle non-power-of-two sizes
+    let src_size = src_img.dimensions();
+    let mut src_img = match apply_size_policy(src_img, file_name) {
+        Ok((img, warning)) => {
+            if let Some(warning) = warning {
+                writeln!(output, "WARNING: {}", warning).unwrap();
+            }
+
+            img
+        },
+        Err(e) => return Err(format!("Failed to convert '{}': {}", path, e))
+    };
+
+    // Load custom mips, authored for the source size
+    let mut custom_mips = fit_custom_mips(
+        load_custom_mips(file_path, output)?,
+        file_name,
+        src_size,
+        src_img.dimensions()
+    );
+
+    if !custom_mips.is_empty() {
+        let mut levels: Vec<u32> = custom_mips.keys().copied().collect();
+        levels.sort_unstable();
+
+        let levels: Vec<String> = levels.iter().map(u32::to_string).collect();
+        writeln!(output, "Using custom mip levels: {}.", levels.join(", ")).unwrap();
+    }
+
+    // Pick the channel single channel formats are read from
+    if let Err(e) = apply_source_channel(
+        iter::once(&mut src_img).chain(custom_mips.values_mut()),
+        file_name,
+        format,
+        output
+    ) {
+        return Err(format!("Failed to convert '{}': {}", path, e));
+    }
+
+    // Flip and renormalize normal maps
+    apply_normal_preprocessing(
+        iter::once(&mut src_img).chain(custom_mips.values_mut()),
+        file_name,
+        &stripped_file_name,
+        format,
+        output
+    );
+
+    // Convert image to bimage format
+    match convert_to_bimage(
+        src_img,
+        custom_mips,
+        file_name.to_owned(),
+        stripped_file_name,
+        format,
+        compress
+    ) {
+        Ok(vec) => Ok(vec),
+        Err(e) => Err(format!("Failed to convert '{}' to DDS: {}", path, e))
+    }
+}
+
+// Load the six faces of a cubemap, from '$face0' to '$face5' files, a cross image or an equirectangular panorama
+fn load_cubemap_faces(
+    file_path: &Path, file_name: &str, output: &mut String
+) -> Result<Vec<RgbaImage>, String> {
+    if utils::cube_face_index(file_path).is_none() {
+        let path = file_path.display();
+        let img = load_image(file_path, output).map_err(|e| format!("Failed to load '{}': {}", path, e))?;
+
+        if !file_name.contains("$equirect") {
+            return split_cube_cross(&img).map_err(|e| format!("Failed to convert '{}': {}", path, e));
+        }
+
+        // Default to a quarter of the panorama's width, rounded down to a power of two
+        let face_size = match utils::parse_token::<u32>(file_name, "facesize")? {
+            Some(0) => return Err("Face size must be greater than 0".into()),
+            Some(face_size) => face_size,
+            None => 1 << (img.width() / 4).max(1).ilog2()
+        };
+
+        let filter = utils::parse_token(file_name, "filter")?.unwrap_or(SampleFilter::Bilinear);
+        writeln!(
+            output,
+            "Resampling panorama into {}x{} faces.",
+            face_size, face_size
+        )
+        .unwrap();
+
+        return Ok(cubemap::equirect_to_cube_faces(&img, face_size, filter));
+    }
+
+    let mut faces = Vec::with_capacity(6);
+
+    for face in 0..6 {
+        let face_path = utils::cube_face_path(file_path, face).unwrap();
+
+        if !face_path.is_file() {
+            return Err(format!("Cubemap face '{}' was not found", face_path.display()));
+        }
+
+        match load_image(&face_path, output) {
+            Ok(img) => faces.push(img),
+            Err(e) => return Err(format!("Failed to load '{}': {}", face_path.display(), e))
+        }
+    }
+
+    Ok(faces)
+}
+
+// Load cubemap faces, convert them to bimage format and compress it
+fn convert_cubemap_file(
+    file_path: &Path, file_name: &str, stripped_file_name: String, compress: bool, output: &mut String
+) -> Result<Vec<u8>, String> {
+    // Get target format
+    let format = get_target_format(file_name, &stripped_file_name);
+
+    // Load faces
+    let mut faces = load_cubemap_faces(file_path, file_name, output)?;
+    writeln!(
+        output,
+        "Building cubemap with {}x{} faces.",
+        faces[0].width(),
+        faces[0].height()
+    )
+    .unwrap();
+
+    // Handle non-power-of-two sizes
+    faces = match apply_size_policy_to_all(faces, file_name, output) {
+        Ok(faces) => faces,
+        Err(e) => return Err(format!("Failed to convert '{}': {}", file_path.display(), e))
+    };
+
+    if let Err(e) = apply_source_channel(faces.iter_mut(), file_name, format, output) {
+        return Err(format!("Failed to convert '{}': {}", file_path.display(), e));
+    }
+
+    // Convert faces to bimage format
+    match convert_cubemap_to_bimage(faces, file_name.to_owned(), stripped_file_name, format, compress) {
+        Ok(vec) => Ok(vec),
+        Err(e) => Err(format!(
+            "Failed to convert '{}' to DDS: {}",
+            file_path.display(),
+            e
+        ))
+    }
+}
+
+// Load every slice of an array or volume texture, from '$slice0' to the first missing one
+fn load_slices(file_path: &Path, output: &mut String) -> Result<Vec<RgbaImage>, String> {
+    let mut slices = Vec::new();
+
+    for slice in 0.. {
+        let slice_path = utils::slice_path(file_path, slice).unwrap();
+
+        if !slice_path.is_file() {
+            break;
+        }
+
+        match load_image(&slice_path, output) {
+            Ok(img) => slices.push(img),
+            Err(e) => return Err(format!("Failed to load '{}': {}", slice_path.display(), e))
+        }
+    }
+
+    Ok(slices)
+}
+
+// Load slices, convert them to bimage format and compress it
+fn convert_slices_file(
+    file_path: &Path, file_name: &str, stripped_file_name: String, compress: bool, output: &mut String
+) -> Result<Vec<u8>, String> {
+    // Get target format
+    let format = get_target_format(file_name, &stripped_file_name);
+
+    // Load slices
+    let mut slices = load_slices(file_path, output)?;
+
+    let kind = match file_name.contains("$volume") {
+        true => "volume",
+        false => "array"
+    };
+
+    writeln!(output, "Building {} texture with {} slices.", kind, slices.len()).unwrap();
+
+    // Handle non-power-of-two sizes
+    slices = match apply_size_policy_to_all(slices, file_name, output) {
+        Ok(slices) => slices,
+        Err(e) => return Err(format!("Failed to convert '{}': {}", file_path.display(), e))
+    };
+
+    if let Err(e) = apply_source_channel(slices.iter_mut(), file_name, format, output) {
+        return Err(format!("Failed to convert '{}': {}", file_path.display(), e));
+    }
+
+    // Convert slices to bimage format
+    match convert_slices_to_bimage(slices, file_name.to_owned(), stripped_file_name, format, compress) {
+        Ok(vec) => Ok(vec),
+        Err(e) => Err(format!(
+            "Failed to convert '{}' to DDS: {}",
+            file_path.display(),
+            e
+        ))
+    }
+}
+
+// Image extensions accepted as flipbook frames
+const FRAME_EXTENSIONS: [&str; 9] = ["png", "tga", "jpg", "jpeg", "bmp", "tif", "tiff", "webp", "hdr"];
+
+// Load every image in a directory as a flipbook frame, sorted by name
+fn load_frames(dir_path: &Path, output: &mut String) -> Result<Vec<RgbaImage>, String> {
+    let entries = match fs::read_dir(dir_path) {
+        Ok(entries) => entries,
+        Err(e) => return Err(format!("Failed to read '{}': {}", dir_path.display(), e))
+    };
+
+    let mut frame_paths: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .is_some_and(|extension| FRAME_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        })
+        .collect();
+
+    frame_paths.sort();
+
+    if frame_paths.is_empty() {
+        return Err(format!("No frames were found in '{}'", dir_path.display()));
+    }
+
+    let mut frames = Vec::with_capacity(frame_paths.len());
+
+    for frame_path in frame_paths {
+        match load_image(&frame_path, output) {
+            Ok(img) => frames.push(img),
+            Err(e) => return Err(format!("Failed to load '{}': {}", frame_path.display(), e))
+        }
+    }
+
+    Ok(frames)
+}
+
+// Decode every frame of an animated PNG or GIF, returns None if the image isn't animated
+fn load_animation_frames(path: &Path) -> Result<Option<Vec<RgbaImage>>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to load '{}': {}", path.display(), e))?;
+
+    let frames = match image::guess_format(&bytes) {
+        Ok(ImageFormat::Png) => {
+            let decoder =
+                PngDecoder::new(Cursor::new(&bytes)).and_then(|decoder| match decoder.is_apng()? {
+                    true => decoder.apng().map(Some),
+                    false => Ok(None)
+                });
+
+            match decoder {
+                Ok(Some(decoder)) => decoder.into_frames().collect_frames(),
+                Ok(None) => return Ok(None),
+                Err(e) => Err(e)
+            }
+        },
+        Ok(ImageFormat::Gif) => {
+            GifDecoder::new(Cursor::new(&bytes)).and_then(|decoder| decoder.into_frames().collect_frames())
+        },
+        _ => return Ok(None)
+    };
+
+    match frames {
+        // Single frame images are converted as usual
+        Ok(frames) if frames.len() < 2 => Ok(None),
+        Ok(frames) => Ok(Some(frames.into_iter().map(Frame::into_buffer).collect())),
+        Err(e) => Err(format!("Failed to load '{}': {}", path.display(), e))
+    }
+}
+
+// Pack frames into a flipbook atlas, following the '$stride', '$columns', '$padding' and '$maxatlas' tokens
+fn pack_frames(
+    mut frames: Vec<RgbaImage>, file_name: &str, output: &mut String
+) -> Result<(RgbaImage, AtlasLayout), String> {
+    // Only keep every Nth frame
+    match utils::parse_token::<usize>(file_name, "stride")? {
+        Some(0) => return Err("Frame stride must be greater than 0".into()),
+        Some(stride) => frames = frames.into_iter().step_by(stride).collect(),
+        None => ()
+    }
+
+    let columns = utils::parse_token(file_name, "columns")?;
+    let padding = get_atlas_padding(file_name)?;
+
+    // Shrink frames so the atlas fits in the maximum size
+    if let (Some(max_size), Some(first_frame)) =
+        (utils::parse_token::<u32>(file_name, "maxatlas")?, frames.first())
+    {
+        let (frame_width, frame_height) = first_frame.dimensions();
+        let layout = AtlasLayout::new(frames.len() as u32, frame_width, frame_height, columns, padding)?;
+
+        match layout.fit_frame_size(max_size) {
+            Some(frame_size) if frame_size == (frame_width, frame_height) => (),
+            Some((new_width, new_height)) => {
+                writeln!(
+                    output,
+                    "WARNING: Frames were shrunk from {}x{} to {}x{} to fit in a {}x{} atlas.",
+                    frame_width, frame_height, new_width, new_height, max_size, max_size
+                )
+                .unwrap();
+
+                frames = frames
+                    .into_iter()
+                    .map(|frame| resize_image(frame, new_width, new_height))
+                    .collect();
+            },
+            None => {
+                return Err(format!(
+                    "{} frames don't fit in a {}x{} atlas",
+                    frames.len(),
+                    max_size,
+                    max_size
+                ))
+            },
+        }
+    }
+
+    atlas::build_atlas(&frames, columns, padding)
+}
+
+// Pack frames into a flipbook atlas, convert it to bimage format and compress it
+fn convert_frames(
+    frames: Vec<RgbaImage>, file_path: &Path, file_name: &str, stripped_file_name: String, compress: bool,
+    output: &mut String
+) -> Result<(Vec<u8>, AtlasLayout), String> {
+    let (atlas_img, layout) = match pack_frames(frames, file_name, output) {
+        Ok(atlas) => atlas,
+        Err(e) => return Err(format!("Failed to convert '{}': {}", file_path.display(), e))
+    };
+
+    writeln!(
+        output,
+        "Packed {} frames into a {}x{} grid.",
+        layout.frame_count, layout.columns, layout.rows
+    )
+    .unwrap();
+
+    // Convert atlas to bimage format
+    let bim_bytes = convert_rgba_image(
+        atlas_img,
+        file_path,
+        file_name,
+        stripped_file_name,
+        compress,
+        output
+    )?;
+    Ok((bim_bytes, layout))
+}
+
+// Rasterize a font into a distance field atlas, following the '$chars', '$glyphsize' and '$spread' tokens
+fn build_font_atlas(file_path: &Path, file_name: &str, output: &mut String) -> Result<FontAtlas, String> {
+    let font_bytes = fs::read(file_path).map_err(|e| e.to_string())?;
+
+    // Default to printable ASCII
+    let chars = match utils::get_token_value(file_name, "chars") {
+        Some(charset) => font::parse_charset(charset)?,
+        None => (' '..='~').collect()
+    };
+
+    let glyph_size = match utils::parse_token::<u32>(file_name, "glyphsize")? {
+        Some(0) => return Err("Glyph size must be greater than 0".into()),
+        Some(glyph_size) => glyph_size,
+        None => 64
+    };
+
+    let spread = match utils::parse_token::<f32>(file_name, "spread")? {
+        Some(spread) if spread <= 0.0 => return Err("SDF spread must be greater than 0".into()),
+        Some(spread) => spread,
+        None => 4.0
+    };
+
+    let font_atlas = font::build_font_atlas(&font_bytes, &chars, glyph_size, spread)?;
+
+    if font_atlas.glyphs.len() < chars.len() {
+        writeln!(
+            output,
+            "WARNING: {} characters are missing from the font, skipping them.",
+            chars.len() - font_atlas.glyphs.len()
+        )
+        .unwrap();
+    }
+
+    writeln!(
+        output,
+        "Rasterized {} glyphs into a {}x{} distance field atlas.",
+        font_atlas.glyphs.len(),
+        font_atlas.image.width(),
+        font_atlas.image.height()
+    )
+    .unwrap();
+
+    Ok(font_atlas)
+}
+
+// Rasterize a font into a distance field atlas, convert it to bimage format and compress it
+fn convert_font_file(
+    file_path: &Path, file_name: &str, stripped_file_name: String, compress: bool, output: &mut String
+) -> Result<(Vec<u8>, String), String> {
+    let font_atlas = match build_font_atlas(file_path, file_name, output) {
+        Ok(font_atlas) => font_atlas,
+        Err(e) => return Err(format!("Failed to convert '{}': {}", file_path.display(), e))
+    };
+
+    // Convert atlas to bimage format
+    let glyphs_json = font_atlas.to_json();
+    let bim_bytes = convert_rgba_image(
+        font_atlas.image,
+        file_path,
+        file_name,
+        stripped_file_name,
+        compress,
+        output
+    )?;
+    Ok((bim_bytes, glyphs_json))
+}
+
+// Get the size of the texture an SVG document replaces, i.e. its path without the '.svg' extension, if it exists
+fn get_replaced_texture_size(file_path: &Path) -> Option<(u32, u32)> {
+    let replaced_path = file_path.with_extension("");
+
+    if !replaced_path.is_file() {
+        return None;
+    }
+
+    match read_bimage(&replaced_path) {
+        Some(bim_result) => {
+            let bim_header = BIMHeader::from_bytes(&bim_result.ok()?)?;
+            Some((bim_header.pixel_width, bim_header.pixel_height))
+        },
+        None => load_image(&replaced_path, &mut String::new())
+            .ok()
+            .map(|img| img.dimensions())
+    }
+}
+
+// Rasterize an SVG document at the '$svgsize=N' width, the size of the texture it replaces or its own size
+fn rasterize_svg_file(file_path: &Path, file_name: &str, output: &mut String) -> Result<RgbaImage, String> {
+    let data = fs::read(file_path).map_err(|e| e.to_string())?;
+    let tree = svg::parse_svg(&data, file_path.parent())?;
+    let (svg_width, svg_height) = svg::svg_size(&tree);
+
+    let (width, height) = match utils::parse_token::<u32>(file_name, "svgsize")? {
+        Some(0) => return Err("SVG size must be greater than 0".into()),
+        Some(width) => (
+            width,
+            ((svg_height as f64 * width as f64 / svg_width as f64).round() as u32).max(1)
+        ),
+        None => match get_replaced_texture_size(file_path) {
+            Some(size) => {
+                writeln!(output, "Matching the size of the texture being replaced.").unwrap();
+                size
+            },
+            None => (svg_width, svg_height)
+        }
+    };
+
+    writeln!(output, "Rasterizing SVG at {}x{}.", width, height).unwrap();
+    svg::rasterize_svg(&tree, width, height)
+}
+
+// Rasterize an SVG document, convert it to bimage format and compress it
+fn convert_svg_file(
+    file_path: &Path, file_name: &str, stripped_file_name: String, compress: bool, output: &mut String
+) -> Result<Vec<u8>, String> {
+    let src_img = match rasterize_svg_file(file_path, file_name, output) {
+        Ok(img) => img,
+        Err(e) => return Err(format!("Failed to convert '{}': {}", file_path.display(), e))
+    };
+
+    convert_rgba_image(
+        src_img,
+        file_path,
+        file_name,
+        stripped_file_name,
+        compress,
+        output
+    )
+}
+
+// Assemble an image from the channels of the images listed in a channel packing recipe
+fn pack_recipe_channels(file_path: &Path, output: &mut String) -> Result<RgbaImage, String> {
+    let path = file_path.display();
+
+    let text = fs::read_to_string(file_path).map_err(|e| format!("Failed to load '{}': {}", path, e))?;
+    let recipe = PackRecipe::parse(&text, file_path.parent().unwrap())
+        .map_err(|e| format!("Failed to convert '{}': {}", path, e))?;
+
+    // Load every image once, even if several channels read from it
+    let mut images = HashMap::new();
+
+    for image_path in recipe.image_paths() {
+        match load_image(image_path, output) {
+            Ok(img) => {
+                images.insert(image_path, img);
+            },
+            Err(e) => return Err(format!("Failed to load '{}': {}", image_path.display(), e))
+        }
+    }
+
+    writeln!(output, "Packing channels from {} images.", images.len()).unwrap();
+    recipe
+        .pack(&images)
+        .map_err(|e| format!("Failed to convert '{}': {}", path, e))
+}
+
+// Assemble an image from a channel packing recipe, convert it to bimage format and compress it
+fn convert_pack_file(
+    file_path: &Path, file_name: &str, stripped_file_name: String, compress: bool, output: &mut String
+) -> Result<Vec<u8>, String> {
+    let src_img = pack_recipe_channels(file_path, output)?;
+
+    convert_rgba_image(
+        src_img,
+        file_path,
+        file_name,
+        stripped_file_name,
+        compress,
+        output
+    )
+}
+
+// Get the file name and output path of a texture made along with another one, named after the other one's
+// bimage without its tokens and with the given stripped name (e.g. 'rock_h.tga$normals' -> 'rock_n.tga')
+fn get_companion_texture_path(bim_path: &Path, stripped_file_name: &str) -> (String, PathBuf) {
+    // Keep the extensions (e.g. '.tga') after the stripped name
+    let bim_name = bim_path.file_name().unwrap().to_str().unwrap();
+    let game_name = bim_name.split('$').next().unwrap();
+
+    let file_name = match game_name.find('.') {
+        Some(dot) => stripped_file_name.to_owned() + &game_name[dot..],
+        None => stripped_file_name.to_owned()
+    };
+
+    let path = get_unused_path(bim_path.with_file_name(&file_name).to_str().unwrap(), "");
+    (file_name, path)
+}
+
+// Get the stripped name of the normal map to derive from a heightmap with '$normals' in its name, if any
+// Heightmaps named '_h' get a matching '_n', other ones get '_n' appended
+fn get_height_normal_map_name(file_name: &str, stripped_file_name: &str) -> Option<String> {
+    let material_kind = TextureMaterialKind::from_filename(
+        file_name.to_owned(),
+        stripped_file_name.to_owned(),
+        get_target_format(file_name, stripped_file_name)
+    );
+
+    if !file_name.contains("$normals") || material_kind != TextureMaterialKind::TmkHeightmap {
+        return None;
+    }
+
+    let base_name = stripped_file_name
+        .strip_suffix("_h")
+        .unwrap_or(stripped_file_name);
+    Some(format!("{}_n", base_name))
+}
+
+// Derive a normal map from a heightmap following the '$strength=N' and '$filter=sobel|scharr' tokens,
+// convert it to bimage format and compress it
+fn convert_height_to_normal_file(
+    height_img: &RgbaImage, file_path: &Path, file_name: &str, normal_stripped_file_name: String,
+    bim_path: &Path, compress: bool, output: &mut String
+) -> Result<(Vec<u8>, PathBuf), String> {
+    let path = file_path.display();
+
+    let strength = match utils::parse_token::<f32>(file_name, "strength") {
+        Ok(Some(strength)) if strength <= 0.0 => {
+            return Err(format!(
+                "Failed to convert '{}': Strength must be greater than 0",
+                path
+            ))
+        },
+        Ok(strength) => strength.unwrap_or(2.0),
+        Err(e) => return Err(format!("Failed to convert '{}': {}", path, e))
+    };
+
+    let filter = match utils::parse_token::<HeightFilter>(file_name, "filter") {
+        Ok(filter) => filter.unwrap_or(HeightFilter::Sobel),
+        Err(e) => return Err(format!("Failed to convert '{}': {}", path, e))
+    };
+
+    writeln!(
+        output,
+        "Deriving normal map with a strength of {} and the {:?} filter.",
+        strength, filter
+    )
+    .unwrap();
+
+    let normal_img = normals::height_to_normal_map(height_img, strength, filter);
+
+    let (normal_file_name, normal_path) = get_companion_texture_path(bim_path, &normal_stripped_file_name);
+
+    let normal_bytes = convert_rgba_image(
+        normal_img,
+        &normal_path,
+        &normal_file_name,
+        normal_stripped_file_name,
+        compress,
+        output
+    )?;
+    Ok((normal_bytes, normal_path))
+}
+
+// Convert the metal/roughness textures listed in a recipe to albedo, '_s' specular and '_g' smoothness bimages,
+// named after the recipe without its tokens (e.g. 'rock.pbr' -> 'rock.tga', 'rock_s.tga' and 'rock_g.tga')
+fn convert_pbr_file(
+    file_path: &Path, file_name: &str, stripped_file_name: &str, compress: bool, ktx2_export: Option<&str>,
+    output: &mut String
+) -> Result<Vec<(Vec<u8>, PathBuf)>, String> {
+    let path = file_path.display();
+
+    let text = fs::read_to_string(file_path).map_err(|e| format!("Failed to load '{}': {}", path, e))?;
+    let recipe = PbrRecipe::parse(&text, file_path.parent().unwrap())
+        .map_err(|e| format!("Failed to convert '{}': {}", path, e))?;
+
+    // Load every image once, even if several inputs read from it
+    let mut images = HashMap::new();
+
+    for image_path in recipe.image_paths() {
+        match load_image(image_path, output) {
+            Ok(img) => {
+                images.insert(image_path, img);
+            },
+            Err(e) => return Err(format!("Failed to load '{}': {}", image_path.display(), e))
+        }
+    }
+
+    writeln!(
+        output,
+        "Converting metal/roughness textures to specular/smoothness."
+    )
+    .unwrap();
+
+    let derived_imgs = recipe
+        .convert(&images)
+        .map_err(|e| format!("Failed to convert '{}': {}", path, e))?;
+
+    // Let the material kinds be detected from the names, and pass the recipe's tokens on to every texture
+    let bim_path = get_bimage_output_path(file_path, file_name);
+    let (tokens, _) = utils::split_extension(file_name);
+    let tokens = tokens.find('$').map_or("", |start| &tokens[start..]);
+
+    // The KTX2 copies are made from the uncompressed bimages
+    let compress_now = compress && ktx2_export.is_none();
+    let mut new_files = Vec::new();
+
+    for (img, suffix) in derived_imgs.into_iter().zip(["", "_s", "_g"]) {
+        let derived_stripped_file_name = stripped_file_name.to_owned() + suffix;
+        let (derived_file_name, derived_path) =
+            get_companion_texture_path(&bim_path, &derived_stripped_file_name);
+
+        let bim_bytes = convert_rgba_image(
+            img,
+            &derived_path,
+            &(derived_file_name + tokens),
+            derived_stripped_file_name,
+            compress_now,
+            output
+        )?;
+
+        push_bimage_files(
+            file_path,
+            bim_bytes,
+            derived_path,
+            ktx2_export,
+            compress,
+            &mut new_files
+        )?;
+    }
+
+    Ok(new_files)
+}
+
+// Add a bimage to the files to write, along with its KTX2 copy if one should be exported
+// The bimage must still be uncompressed when exporting KTX2, and is compressed afterwards
+fn push_bimage_files(
+    file_path: &Path, mut bim_bytes: Vec<u8>, bim_path: PathBuf, ktx2_export: Option<&str>, compress: bool,
+    new_files: &mut Vec<(Vec<u8>, PathBuf)>
+) -> Result<(), String> {
+    if let Some(ktx2_export) = ktx2_export {
+        // Export KTX2 with the exact same data as the bimage
+        match convert_bimage_to_ktx2(&bim_bytes) {
+            Ok(ktx2_bytes) => {
+                new_files.push((ktx2_bytes, get_unused_path(bim_path.to_str().unwrap(), "ktx2")))
+            },
+            Err(e) => {
+                return Err(format!(
+                    "Failed to export '{}' to KTX2: {}",
+                    file_path.display(),
+                    e
+                ))
+            },
+        }
+
+        if ktx2_export == "only" {
+            return Ok(());
+        }
+
+        if compress {
+            bim_bytes = kraken_compress(&mut bim_bytes)?;
+        }
+    }
+
+    new_files.push((bim_bytes, bim_path));
+    Ok(())
+}
+
+// Get a path that doesn't exist yet, appending -i to the name with the least possible number if needed
+fn get_unused_path(trunc_path: &str, extension: &str) -> PathBuf {
+    let dot = match extension {
+        "" => "",
+        _ => "."
+    };
+
+    let mut new_path = trunc_path.to_owned() + dot + extension;
+    let mut i = 2_u32;
+
+    while Path::new(&new_path).exists() {
+        new_path = trunc_path.to_owned() + "-" + &i.to_string() + dot + extension;
+        i += 1;
+    }
+
+    PathBuf::from(new_path)
+}
+
+// Get output path for the bimage converted from the given file
+fn get_bimage_output_path(file_path: &Path, file_name: &str) -> PathBuf {
+    let new_extension: &str;
+    let trunc_path: PathBuf;
+
+    if !file_name.contains('$') {
+        if file_path.file_stem().unwrap().to_str().unwrap().ends_with(".png") {
+            new_extension = "png";
+        }
+        else {
+            new_extension = "tga"
+        }
+
+        trunc_path = file_path.with_extension("");
+    }
+    else {
+        // Drop the extension, unless the name ends in its tokens
+        let (file_stem, _) = utils::split_extension(file_name);
+        new_extension = "";
+        trunc_path = file_path.with_file_name(file_stem);
+    }
+
+    // Prevent overwriting
+    get_unused_path(trunc_path.to_str().unwrap(), new_extension)
+}
+
+// Convert the given file, returning the bytes and paths of the files to write
+fn convert_file(
+    path: &str, file_name: &str, stripped_file_name: String, output: &mut String
+) -> Result<Vec<(Vec<u8>, PathBuf)>, String> {
+    // Check if image should be compressed
+    let compress = env::var("AUTOHECKIN_SKIP_COMPRESSION").is_err();
+
+    // Check if a KTX2 copy should be exported alongside the bimage, or instead of it ("only")
+    let ktx2_export = env::var("AUTOHECKIN_EXPORT_KTX2").ok();
+
+    convert_file_with(
+        path,
+        file_name,
+        stripped_file_name,
+        compress,
+        ktx2_export.as_deref(),
+        output
+    )
+}
+
+// Convert the given file with the given compression and KTX2 export settings
+fn convert_file_with(
+    path: &str, file_name: &str, stripped_file_name: String, compress: bool, ktx2_export: Option<&str>,
+    output: &mut String
+) -> Result<Vec<(Vec<u8>, PathBuf)>, String> {
+    let file_path = Path::new(path);
+
+    // Export bimages to DDS
+    if let Some(bim_result) = read_bimage(file_path) {
+        let bim = bim_result.map_err(|e| format!("Failed to load '{}': {}", path, e))?;
+
+        return match convert_bimage_to_dds(&bim) {
+            Ok(dds_bytes) => Ok(vec![(dds_bytes, get_unused_path(path, "dds"))]),
+            Err(e) => Err(format!("Failed to export '{}' to DDS: {}", path, e))
+        };
+    }
+
+    // The KTX2 copy is made from the uncompressed bimage, so compress it later
+    let compress_now = compress && ktx2_export.is_none();
+
+    // Metal/roughness recipes make three textures at once
+    if utils::is_pbr_file(file_name) {
+        return convert_pbr_file(
+            file_path,
+            file_name,
+            &stripped_file_name,
+            compress,
+            ktx2_export,
+            output
+        );
+    }
+
+    // Cubemaps are made from a cross image ('$cube'), a panorama ('$equirect') or six face images ('$face0' to '$face5')
+    let face_index = utils::cube_face_index(file_path);
+    let is_cubemap = file_name.contains("$cube") || file_name.contains("$equirect") || face_index.is_some();
+
+    // Arrays and volumes are made from numbered slice images ('$slice0' onwards)
+    let slice_index = utils::slice_index(file_path);
+
+    // Heightmaps with '$normals' in their name also get a matching normal map
+    let normal_stripped_file_name = match file_path.is_file() {
+        true => get_height_normal_map_name(file_name, &stripped_file_name),
+        false => None
+    };
+
+    // Fonts are rasterized into a distance field atlas, with a sidecar describing their glyphs
+    let is_font = utils::is_font_file(file_name);
+
+    // Directories of frames and animated images are packed into a flipbook atlas,
+    // with a sidecar describing its layout
+    let mut animation_frames = match file_path.is_file() && !is_cubemap && slice_index.is_none() && !is_font {
+        true => load_animation_frames(file_path)?,
+        false => None
+    };
+
+    let mut sidecar = None;
+
+    // Keep the decoded heightmap to derive its normal map from
+    let mut height_img = None;
+
+    // Convert everything else to bimage format
+    let bim_bytes = match dds::read_dds(file_path) {
+        _ if file_path.is_dir() || animation_frames.is_some() => {
+            let frames = match animation_frames.take() {
+                Some(frames) => frames,
+                None => load_frames(file_path, output)?
+            };
+
+            let (bim_bytes, layout) = convert_frames(
+                frames,
+                file_path,
+                file_name,
+                stripped_file_name,
+                compress_now,
+                output
+            )?;
+            sidecar = Some(layout.to_json());
+            bim_bytes
+        },
+        _ if is_font => {
+            let (bim_bytes, glyphs_json) =
+                convert_font_file(file_path, file_name, stripped_file_name, compress_now, output)?;
+            sidecar = Some(glyphs_json);
+            bim_bytes
+        },
+        _ if utils::is_svg_file(file_name) => {
+            convert_svg_file(file_path, file_name, stripped_file_name, compress_now, output)?
+        },
+        _ if utils::is_pack_file(file_name) => {
+            convert_pack_file(file_path, file_name, stripped_file_name, compress_now, output)?
+        },
+        _ if is_cubemap => {
+            convert_cubemap_file(file_path, file_name, stripped_file_name, compress_now, output)?
+        },
+        _ if slice_index.is_some() => {
+            convert_slices_file(file_path, file_name, stripped_file_name, compress_now, output)?
+        },
+        Some(Ok(dds)) => {
+            writeln!(output, "Wrapping DDS data as is.").unwrap();
+
+            match convert_dds_to_bimage(
+                dds,
+                file_name.to_owned(),
+                stripped_file_name,
+                compress_now,
+                output
+            ) {
+                Ok(vec) => vec,
+                Err(e) => return Err(format!("Failed to convert '{}' to DDS: {}", path, e))
+            }
+        },
+        Some(Err(e)) => {
+            writeln!(output, "WARNING: Can't wrap DDS as is ({}), re-encoding it.", e).unwrap();
+            convert_image_file(file_path, file_name, stripped_file_name, compress_now, output)?
+        },
+        None if normal_stripped_file_name.is_some() => {
+            let src_img =
+                load_image(file_path, output).map_err(|e| format!("Failed to load '{}': {}", path, e))?;
+            height_img = Some(src_img.clone());
+
+            convert_rgba_image(
+                src_img,
+                file_path,
+                file_name,
+                stripped_file_name,
+                compress_now,
+                output
+            )?
+        },
+        None => convert_image_file(file_path, file_name, stripped_file_name, compress_now, output)?
+    };
+
+    // Name textures made from several images after the first one, without its '$face0' or '$slice0' token
+    let bim_path = match face_index.or(slice_index) {
+        Some(_) => {
+            let base_path = utils::strip_last_token(file_path).unwrap();
+            get_bimage_output_path(&base_path, base_path.file_name().unwrap().to_str().unwrap())
+        },
+        None => get_bimage_output_path(file_path, file_name)
+    };
+    let mut new_files = Vec::new();
+
+    if let Some(sidecar) = sidecar {
+        new_files.push((
+            sidecar.into_bytes(),
+            get_unused_path(bim_path.to_str().unwrap(), "json")
+        ));
+    }
+
+    if let Some(normal_stripped_file_name) = normal_stripped_file_name {
+        let height_img = match height_img {
+            Some(img) => img,
+            None => load_image(file_path, output).map_err(|e| format!("Failed to load '{}': {}", path, e))?
+        };
+
+        let (normal_bytes, normal_path) = convert_height_to_normal_file(
+            &height_img,
+            file_path,
+            file_name,
+            normal_stripped_file_name,
+            &bim_path,
+            compress_now,
+            output
+        )?;
+
+        push_bimage_files(
+            file_path,
+            normal_bytes,
+            normal_path,
+            ktx2_export,
+            compress,
+            &mut new_files
+        )?;
+    }
+
+    push_bimage_files(
+        file_path,
+        bim_bytes,
+        bim_path,
+        ktx2_export,
+        compress,
+        &mut new_files
+    )?;
+    Ok(new_files)
+}
+
+// Load textures, convert them to bim, and compress them
+fn handle_textures(paths: Vec<String>) -> u32 {
+    // Custom mips, cubemap faces and slices are loaded along with their texture, so don't convert them on their own
+    let (sibling_paths, paths): (Vec<String>, Vec<String>) = paths.into_iter().partition(|path| {
+        utils::custom_mip_level(Path::new(path)).is_some()
+            || utils::cube_face_index(Path::new(path)).is_some_and(|face| face > 0)
+            || utils::slice_index(Path::new(path)).is_some_and(|slice| slice > 0)
+    });
+
+    for path in sibling_paths {
+        match () {
+            _ if utils::custom_mip_level(Path::new(&path)).is_some() => println!(
+                "\nSkipping '{}', custom mips are loaded along with their texture.",
+                path
+            ),
+            _ if utils::cube_face_index(Path::new(&path)).is_some() => println!(
+                "\nSkipping '{}', cubemap faces are loaded along with '$face0'.",
+                path
+            ),
+            _ => println!("\nSkipping '{}', slices are loaded along with '$slice0'.", path)
+        }
+    }
+
+    let paths_len = paths.len() as u32;
+
+    // Thread handles
+    let mut handles = Vec::new();
+
+    // Count successful conversions
+    let counter = Arc::new(Mutex::new(0));
+
+    // Iterate through args
+    for path in paths {
+        let counter = counter.clone();
+
+        let handle = thread::spawn(move || {
+            let mut output = String::default();
+            writeln!(&mut output).unwrap();
+
+            // Get texture's format and stripped filename
+            let file_path = Path::new(&path);
+            let file_name = file_path.file_name().unwrap().to_str().unwrap().to_owned();
+            let stripped_file_name = file_name
+                .split('$')
+                .next()
+                .unwrap()
+                .split('.')
+                .next()
+                .unwrap()
+                .to_owned();
+
+            writeln!(&mut output, "Converting '{}'...", file_name).unwrap();
+
+            // Check if given path exists, as a file or a directory of frames
+            if !file_path.exists() {
+                writeln!(&mut output, "ERROR: '{}' was not found.", path).unwrap();
+                return output;
+            }
+
+            // Convert file
+            let new_files = match convert_file(&path, &file_name, stripped_file_name, &mut output) {
+                Ok(new_files) => new_files,
+                Err(e) => {
+                    writeln!(&mut output, "ERROR: {}", e).unwrap();
                     return output;
                 }
+            };
+
+            // Adquire lock
+            let mut counter = counter.lock().unwrap();
+            let mut new_file_names = Vec::new();
+
+            for (new_file_bytes, new_file_path) in new_files {
+                // Write output file
+                let mut output_file = match File::create(new_file_path.to_str().unwrap()) {
+                    Ok(f) => f,
+                    Err(e) => {
+                        writeln!(&mut output, "ERROR: Failed to create output file: {}", e).unwrap();
+                        return output;
+                    }
+                };
+
+                match output_file.write_all(&new_file_bytes) {
+                    Ok(_) => (),
+                    Err(e) => {
+                        writeln!(&mut output, "ERROR: Failed to write to output file: {}", e).unwrap();
+                        return output;
+                    }
+                }
+
+                // Get filename
+                let new_file_name = new_file_path.file_name().unwrap().to_str().unwrap().to_owned();
+                new_file_names.push(format!("'{}'", new_file_name));
             }
 
             writeln!(
                 &mut output,
-                "Successfully converted '{}' into '{}'.",
-                file_name, new_file_name
+                "Successfully converted '{}' into {}.",
+                file_name,
+                new_file_names.join(" and ")
             )
             .unwrap();
             *counter += 1;
@@ -496,7 +2513,12 @@ fn main() {
     // Display help if no arguments are provided
     if args.is_empty() {
         println!("\nUsage:");
-        println!("{} [texture1.png] [texture2.png] [...]\n", program);
+        println!("{} [texture1.png] [texture2.tga] [...]\n", program);
+        println!(
+            "Supported formats: PNG, TGA, JPEG, BMP, TIFF, WebP, GIF, HDR, SVG and DDS, plus TTF and OTF \
+             fonts."
+        );
+        println!("Bimages are exported to DDS instead.");
         println!("Alternatively, drag files onto this executable.");
 
         // Exit
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+
+use image::{Rgba, RgbaImage};
+
+use crate::channels::Channel;
+
+// Decode a color channel into a normal component, from [0, 255] to [-1, 1]
+fn decode_component(value: u8) -> f32 {
+    value as f32 / 255.0 * 2.0 - 1.0
+}
+
+// Encode a normal component into a color channel, from [-1, 1] to [0, 255]
+fn encode_component(value: f32) -> u8 {
+    ((value * 0.5 + 0.5) * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+// Encode a normal vector into an opaque pixel, with X in red and Y in green for BC5
+pub fn encode_normal([x, y, z]: [f32; 3]) -> Rgba<u8> {
+    Rgba([encode_component(x), encode_component(y), encode_component(z), 255])
+}
+
+// Prepare a normal map, optionally flipping Y (green) and making every vector unit length
+// BC5 has no alpha, so it's made opaque to keep it from weighing the vectors when resizing mips,
+// other formats keep it
+pub fn preprocess_normal_map(img: &mut RgbaImage, flip_y: bool, renormalize: bool, opaque: bool) {
+    for pixel in img.pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        let (x, mut y, z) = (decode_component(r), decode_component(g), decode_component(b));
+
+        if flip_y {
+            y = -y;
+        }
+
+        let length = (x * x + y * y + z * z).sqrt();
+
+        *pixel = match renormalize {
+            // Vectors too short to have a direction point straight out
+            true if length < 1e-6 => encode_normal([0.0, 0.0, 1.0]),
+            true => encode_normal([x / length, y / length, z / length]),
+            false => encode_normal([x, y, z])
+        };
+
+        if !opaque {
+            pixel[3] = a;
+        }
+    }
+}
+
+// Derivative filter used to get slopes from a heightmap
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum HeightFilter {
+    Sobel,
+    Scharr
+}
+
+impl FromStr for HeightFilter {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "sobel" => Ok(HeightFilter::Sobel),
+            "scharr" => Ok(HeightFilter::Scharr),
+            _ => Err(())
+        }
+    }
+}
+
+impl HeightFilter {
+    // Get the smoothing weights across the derivative, for the previous, current and next row or column
+    fn weights(&self) -> [f32; 3] {
+        match self {
+            HeightFilter::Sobel => [1.0, 2.0, 1.0],
+            HeightFilter::Scharr => [3.0, 10.0, 3.0]
+        }
+    }
+}
+
+// Derive a normal map from the luminance of a heightmap, with Y pointing down like the game expects
+// Slopes are scaled by the strength, where a step from black to white over one pixel is a slope of 1
+pub fn height_to_normal_map(img: &RgbaImage, strength: f32, filter: HeightFilter) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let heights: Vec<f32> = img
+        .pixels()
+        .map(|pixel| Channel::Luminance.read(pixel) as f32 / 255.0)
+        .collect();
+
+    // Clamp to the edges
+    let height_at = |x: i64, y: i64| {
+        let x = x.clamp(0, width as i64 - 1) as usize;
+        let y = y.clamp(0, height as i64 - 1) as usize;
+        heights[y * width as usize + x]
+    };
+
+    let weights = filter.weights();
+    let weight_sum: f32 = weights.iter().sum::<f32>() * 2.0;
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        let (mut dx, mut dy) = (0.0, 0.0);
+
+        for (i, weight) in weights.iter().enumerate() {
+            let offset = i as i64 - 1;
+            dx += weight * (height_at(x + 1, y + offset) - height_at(x - 1, y + offset));
+            dy += weight * (height_at(x + offset, y + 1) - height_at(x + offset, y - 1));
+        }
+
+        let (nx, ny) = (-dx / weight_sum * strength, -dy / weight_sum * strength);
+        let length = (nx * nx + ny * ny + 1.0).sqrt();
+        encode_normal([nx / length, ny / length, 1.0 / length])
+    })
+}
@@ -4,4 +4,6 @@
 extern "C" {
     #[link_name = "Kraken_Compress"]
     pub fn kraken_compress(src: *mut u8, src_len: usize, dst: *mut u8, level: i32) -> i32;
+    #[link_name = "Kraken_Decompress"]
+    pub fn kraken_decompress(src: *const u8, src_len: usize, dst: *mut u8, dst_len: usize) -> i32;
 }
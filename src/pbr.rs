@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf}
+};
+
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    channels::{self, ChannelSource},
+    utils::{linear_to_srgb, srgb_to_linear}
+};
+
+// Reflectance of dielectrics at normal incidence, in linear space
+const DIELECTRIC_SPECULAR: f32 = 0.04;
+
+// Recipe to convert metal/roughness textures to albedo, specular and smoothness
+#[derive(Debug, PartialEq)]
+pub struct PbrRecipe {
+    pub basecolor: PathBuf,
+    pub metallic: ChannelSource,
+    pub roughness: ChannelSource
+}
+
+impl PbrRecipe {
+    // Parse a recipe with 'basecolor = image', 'metallic = source' and 'roughness = source' lines,
+    // with sources written like in channel packing recipes (e.g. 'roughness = orm.png:g')
+    // Metallic defaults to 0, for dielectrics
+    pub fn parse(text: &str, base_dir: &Path) -> Result<PbrRecipe, String> {
+        let (mut basecolor, mut metallic, mut roughness) = (None, ChannelSource::Constant(0), None);
+
+        for line in text.lines() {
+            // Skip comments and empty lines
+            let line = line.split('#').next().unwrap().trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let (input, source) = line
+                .split_once('=')
+                .ok_or(format!("Expected 'input = source', found '{}'", line))?;
+
+            match input.trim() {
+                "basecolor" => basecolor = Some(base_dir.join(source.trim())),
+                "metallic" => metallic = ChannelSource::parse(source.trim(), base_dir)?,
+                "roughness" => roughness = Some(ChannelSource::parse(source.trim(), base_dir)?),
+                input => return Err(format!("Invalid input '{}'", input))
+            }
+        }
+
+        Ok(PbrRecipe {
+            basecolor: basecolor.ok_or("The recipe has no basecolor")?,
+            metallic,
+            roughness: roughness.ok_or("The recipe has no roughness")?
+        })
+    }
+
+    // Get the paths of every image used by the recipe, without duplicates
+    pub fn image_paths(&self) -> Vec<&Path> {
+        let mut paths = vec![self.basecolor.as_path()];
+
+        for source in [&self.metallic, &self.roughness] {
+            if let ChannelSource::Image { path, .. } = source {
+                if !paths.contains(&path.as_path()) {
+                    paths.push(path);
+                }
+            }
+        }
+
+        paths
+    }
+
+    // Derive the albedo, specular and smoothness images from the loaded images, which must all be the same size
+    // Metals have no diffuse color and reflect their base color, dielectrics the other way around
+    pub fn convert(&self, images: &HashMap<&Path, RgbaImage>) -> Result<[RgbaImage; 3], String> {
+        let (width, height) = channels::common_size(&self.image_paths(), images)?;
+        let basecolor_img = &images[self.basecolor.as_path()];
+
+        let mut albedo_img = RgbaImage::new(width, height);
+        let mut specular_img = RgbaImage::new(width, height);
+        let mut smoothness_img = RgbaImage::new(width, height);
+
+        for (x, y, pixel) in basecolor_img.enumerate_pixels() {
+            let metallic = self.metallic.read(images, x, y) as f32 / 255.0;
+            let smoothness = 255 - self.roughness.read(images, x, y);
+
+            // Blend in linear space
+            let [r, g, b, a] = pixel.0;
+            let [r, g, b] = [r, g, b].map(srgb_to_linear);
+
+            let albedo = [r, g, b].map(|value| linear_to_srgb(value * (1.0 - metallic)));
+            let specular = [r, g, b]
+                .map(|value| linear_to_srgb(DIELECTRIC_SPECULAR + (value - DIELECTRIC_SPECULAR) * metallic));
+
+            albedo_img.put_pixel(x, y, Rgba([albedo[0], albedo[1], albedo[2], a]));
+            specular_img.put_pixel(x, y, Rgba([specular[0], specular[1], specular[2], 255]));
+            smoothness_img.put_pixel(x, y, Rgba([smoothness, smoothness, smoothness, 255]));
+        }
+
+        Ok([albedo_img, specular_img, smoothness_img])
+    }
+}
@@ -0,0 +1,141 @@
+use image::RgbaImage;
+
+// Stand-in for infinity, so the distance transform never subtracts infinities
+const FAR: f32 = 1e20;
+
+// Squared distance transform of a sampled function in one dimension (Felzenszwalb and Huttenlocher)
+fn distance_transform_1d(f: &[f32], d: &mut [f32], v: &mut [usize], z: &mut [f32]) {
+    let parabola_intersection =
+        |q: usize, p: usize| ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2 * q - 2 * p) as f32;
+
+    // Find the lower envelope of the parabolas rooted at every sample
+    let mut k = 0;
+    v[0] = 0;
+    z[0] = -f32::INFINITY;
+    z[1] = f32::INFINITY;
+
+    for q in 1..f.len() {
+        let mut s = parabola_intersection(q, v[k]);
+
+        while s <= z[k] {
+            k -= 1;
+            s = parabola_intersection(q, v[k]);
+        }
+
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = f32::INFINITY;
+    }
+
+    // Sample the envelope
+    k = 0;
+
+    for (q, distance) in d.iter_mut().enumerate() {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+
+        let offset = q.abs_diff(v[k]) as f32;
+        *distance = offset * offset + f[v[k]];
+    }
+}
+
+// Get the squared distance from every pixel to the nearest pixel whose mask matches the target
+fn squared_distances(mask: &[bool], width: usize, height: usize, target: bool) -> Vec<f32> {
+    let mut distances: Vec<f32> = mask
+        .iter()
+        .map(|inside| match *inside == target {
+            true => 0.0,
+            false => FAR
+        })
+        .collect();
+
+    let size = width.max(height);
+    let (mut f, mut d, mut v, mut z) = (
+        vec![0.0; size],
+        vec![0.0; size],
+        vec![0; size],
+        vec![0.0; size + 1]
+    );
+
+    // Columns first, then rows
+    for x in 0..width {
+        for y in 0..height {
+            f[y] = distances[y * width + x];
+        }
+
+        distance_transform_1d(&f[..height], &mut d[..height], &mut v, &mut z);
+
+        for y in 0..height {
+            distances[y * width + x] = d[y];
+        }
+    }
+
+    for y in 0..height {
+        let row = &mut distances[y * width..(y + 1) * width];
+        f[..width].copy_from_slice(row);
+        distance_transform_1d(&f[..width], &mut d[..width], &mut v, &mut z);
+        row.copy_from_slice(&d[..width]);
+    }
+
+    distances
+}
+
+// Generate a signed distance field from a high resolution glyph image
+// The glyph is read from alpha, or from luminance if the image is opaque, with everything above half inside
+// The spread is the distance in output pixels covered from the edge to full inside or outside
+pub fn generate_sdf(img: &RgbaImage, spread: f32, new_width: u32, new_height: u32) -> RgbaImage {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let is_opaque = img.pixels().all(|pixel| pixel[3] == 255);
+
+    let mask: Vec<bool> = img
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+
+            match is_opaque {
+                true => 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32 >= 127.5,
+                false => a >= 128
+            }
+        })
+        .collect();
+
+    // Distances are measured between pixel centers, so the edge is half a pixel away from both sides
+    let to_inside = squared_distances(&mask, width, height, true);
+    let to_outside = squared_distances(&mask, width, height, false);
+
+    let signed_distances: Vec<f32> = mask
+        .iter()
+        .enumerate()
+        .map(|(i, inside)| match inside {
+            true => to_outside[i].sqrt() - 0.5,
+            false => 0.5 - to_inside[i].sqrt()
+        })
+        .collect();
+
+    // Average the source pixels covered by every output pixel
+    let scale = new_width as f32 / width as f32;
+
+    RgbaImage::from_fn(new_width, new_height, |x, y| {
+        let x0 = x as usize * width / new_width as usize;
+        let x1 = ((x as usize + 1) * width / new_width as usize).max(x0 + 1);
+        let y0 = y as usize * height / new_height as usize;
+        let y1 = ((y as usize + 1) * height / new_height as usize).max(y0 + 1);
+
+        let mut sum = 0.0;
+
+        for source_y in y0..y1 {
+            for source_x in x0..x1 {
+                sum += signed_distances[source_y * width + source_x];
+            }
+        }
+
+        let distance = sum / ((x1 - x0) * (y1 - y0)) as f32 * scale;
+        let value = ((0.5 + distance / (2.0 * spread)) * 255.0)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+
+        image::Rgba([value, value, value, value])
+    })
+}
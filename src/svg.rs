@@ -0,0 +1,47 @@
+use std::path::Path;
+
+use image::RgbaImage;
+use resvg::{tiny_skia, usvg};
+
+// Parse an SVG document, with text drawn using the system fonts
+// Linked images are loaded relative to the given directory
+pub fn parse_svg(data: &[u8], resources_dir: Option<&Path>) -> Result<usvg::Tree, String> {
+    let mut options = usvg::Options {
+        resources_dir: resources_dir.map(Path::to_path_buf),
+        ..Default::default()
+    };
+    options.fontdb_mut().load_system_fonts();
+
+    usvg::Tree::from_data(data, &options).map_err(|e| e.to_string())
+}
+
+// Get the size the SVG document was authored at, rounded to whole pixels
+pub fn svg_size(tree: &usvg::Tree) -> (u32, u32) {
+    let size = tree.size();
+    (
+        (size.width().round() as u32).max(1),
+        (size.height().round() as u32).max(1)
+    )
+}
+
+// Rasterize an SVG document, stretching it to the given size
+pub fn rasterize_svg(tree: &usvg::Tree, width: u32, height: u32) -> Result<RgbaImage, String> {
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or("Invalid rasterization size")?;
+
+    let size = tree.size();
+    let transform =
+        tiny_skia::Transform::from_scale(width as f32 / size.width(), height as f32 / size.height());
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    // Pixmaps are premultiplied, but textures store straight alpha
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+
+    Ok(RgbaImage::from_raw(width, height, pixels).unwrap())
+}
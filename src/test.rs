@@ -1,5 +1,42 @@
+use std::{
+    ops::Deref,
+    sync::atomic::{AtomicU32, Ordering}
+};
+
 use super::*;
 
+// Temporary directory unique to a test, removed when dropped even if the test fails
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+        let path = env::temp_dir().join(format!(
+            "autoheckin_test_{}_{}_{}",
+            name,
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
 #[test]
 fn test_get_texture_material_kind() {
     assert_eq!(
@@ -65,15 +102,8 @@ fn helper_convert_to_bimage(file_path: &str, format: TextureFormat, expected_bim
         .to_owned();
 
     // Load image
-    let mut src_reader = match ImageReader::open(file_path) {
-        Ok(reader) => reader,
-        Err(_) => panic!("Could not load image")
-    };
-
-    src_reader.set_format(ImageFormat::Png);
-
-    let src_img = match src_reader.decode() {
-        Ok(img) => img.into_rgba8(),
+    let src_img = match load_image(Path::new(file_path), &mut String::new()) {
+        Ok(img) => img,
         Err(_) => panic!("Could not load image")
     };
 
@@ -83,7 +113,14 @@ fn helper_convert_to_bimage(file_path: &str, format: TextureFormat, expected_bim
     }
 
     // Convert image to bimage format
-    let bim_bytes = match convert_to_bimage(src_img, file_name.into(), stripped_file_name, format, false) {
+    let bim_bytes = match convert_to_bimage(
+        src_img,
+        HashMap::new(),
+        file_name.into(),
+        stripped_file_name,
+        format,
+        false
+    ) {
         Ok(vec) => vec,
         Err(_) => panic!("Failed to convert to bimage")
     };
@@ -163,3 +200,1528 @@ fn test_convert_to_bimage_6() {
 
     helper_convert_to_bimage(file_path, format, bim_bytes);
 }
+
+#[test]
+fn test_custom_mips() {
+    // Mip 1 is filled with a different alpha value than mip 0
+    let src_img = RgbaImage::from_pixel(8, 8, image::Rgba([0, 0, 0, 10]));
+    let mut custom_mips = HashMap::new();
+    custom_mips.insert(1, RgbaImage::from_pixel(4, 4, image::Rgba([0, 0, 0, 20])));
+
+    let bim_bytes = convert_to_bimage(
+        src_img.clone(),
+        custom_mips,
+        "test.tga$alpha.png".into(),
+        "test".into(),
+        TextureFormat::FmtAlpha,
+        false
+    )
+    .unwrap();
+
+    // Skip header and the 4 mip entries, then mip 0 (8x8)
+    let mip_1_start = 63 + 36 * 4 + 64;
+    assert_eq!(bim_bytes[mip_1_start..mip_1_start + 16], [20_u8; 16]);
+
+    // Mips with the wrong size are rejected
+    let mut custom_mips = HashMap::new();
+    custom_mips.insert(2, RgbaImage::new(4, 4));
+
+    assert!(convert_to_bimage(
+        src_img,
+        custom_mips,
+        "test.tga$alpha.png".into(),
+        "test".into(),
+        TextureFormat::FmtAlpha,
+        false
+    )
+    .is_err());
+}
+
+#[test]
+fn test_custom_mip_paths() {
+    let path = Path::new("./test/test.tga$bc7.png");
+    let mip_path = utils::custom_mip_path(path, 2).unwrap();
+
+    assert_eq!(mip_path, Path::new("./test/test.tga$bc7$mip2.png"));
+    assert_eq!(utils::custom_mip_level(&mip_path), Some(2));
+    assert_eq!(utils::custom_mip_level(path), None);
+    assert_eq!(utils::custom_mip_level(Path::new("mip2.png")), None);
+
+    // Names without an extension end in their tokens
+    let path = Path::new("./test/test.tga$bc7");
+    let mip_path = utils::custom_mip_path(path, 1).unwrap();
+    assert_eq!(mip_path, Path::new("./test/test.tga$bc7$mip1"));
+    assert_eq!(utils::custom_mip_level(&mip_path), Some(1));
+}
+
+#[test]
+fn test_generate_mips() {
+    let src_img = RgbaImage::from_pixel(16, 4, image::Rgba([200, 100, 50, 255]));
+
+    let cascade_mips = generate_mips_cascade(src_img.clone(), HashMap::new(), 5, false);
+    let direct_mips = generate_mips_direct(src_img, HashMap::new(), 5, false);
+
+    // Both methods should give the same result on a flat image
+    assert_eq!(cascade_mips, direct_mips);
+
+    for (level, mip) in cascade_mips.iter().enumerate() {
+        let (mip_width, mip_height) = mip_dimensions(16, 4, level as u32);
+        assert_eq!(mip.len(), mip_width as usize * mip_height as usize * 4);
+        assert_eq!(mip[0..4], [200, 100, 50, 255]);
+    }
+}
+
+#[test]
+fn test_pad_to_block_size() {
+    // Odd width and height
+    let image: Vec<u8> = (0..3 * 2).flat_map(|i| [i as u8; 4]).collect();
+    let (padded, width, height) = pad_to_block_size(image, 3, 2);
+    let expected: Vec<u8> = [0, 1, 2, 2, 3, 4, 5, 5, 3, 4, 5, 5, 3, 4, 5, 5]
+        .iter()
+        .flat_map(|i| [*i; 4])
+        .collect();
+
+    assert_eq!((width, height), (4, 4));
+    assert_eq!(padded, expected);
+
+    // 1x1 mip
+    let (padded, width, height) = pad_to_block_size(vec![1, 2, 3, 4], 1, 1);
+    assert_eq!((width, height), (4, 4));
+    assert_eq!(padded, [1, 2, 3, 4].repeat(16));
+
+    // Non-square, already aligned width
+    let image: Vec<u8> = (0..8 * 5).flat_map(|i| [i as u8; 4]).collect();
+    let (padded, width, height) = pad_to_block_size(image.clone(), 8, 5);
+    assert_eq!((width, height), (8, 8));
+    assert_eq!(padded[..image.len()], image);
+    assert_eq!(padded[8 * 4 * 7..], image[8 * 4 * 4..]);
+
+    // Nothing to pad
+    let image = vec![7_u8; 4 * 8 * 4];
+    assert_eq!(pad_to_block_size(image.clone(), 4, 8), (image, 4, 8));
+}
+
+#[test]
+fn test_compressed_mip_sizes() {
+    let mips = |width: u32, height: u32, count: u32| -> Vec<Vec<u8>> {
+        (0..count)
+            .map(|level| {
+                let (mip_width, mip_height) = mip_dimensions(width, height, level);
+                vec![0_u8; (mip_width * mip_height * 4) as usize]
+            })
+            .collect()
+    };
+    let mip_sizes = |bim_mips: Vec<(Vec<u8>, BIMMipMap)>| -> Vec<(u32, u32)> {
+        bim_mips
+            .into_iter()
+            .map(|(_, bim_mip)| (bim_mip.mip_pixel_width, bim_mip.mip_pixel_height))
+            .collect()
+    };
+
+    // Power-of-two textures store their smallest mips as a whole block
+    let bim_mips = compress_mips(mips(8, 8, 4), 8, 8, TextureFormat::FmtBc1Srgb, 0);
+    assert_eq!(mip_sizes(bim_mips), [(8, 8), (4, 4), (4, 4), (4, 4)]);
+
+    // Non-power-of-two ones keep the actual size of every mip
+    let bim_mips = compress_mips(mips(12, 6, 4), 12, 6, TextureFormat::FmtBc1Srgb, 0);
+    assert_eq!(mip_sizes(bim_mips), [(12, 6), (6, 3), (3, 1), (1, 1)]);
+
+    // Uncompressed mips are never padded
+    let bim_mips = compress_mips(mips(8, 8, 4), 8, 8, TextureFormat::FmtAlpha, 0);
+    assert_eq!(mip_sizes(bim_mips), [(8, 8), (4, 4), (2, 2), (1, 1)]);
+}
+
+#[test]
+fn test_get_mipmap_count() {
+    assert_eq!(get_mipmap_count("test.png", 256, 64), Ok(9));
+    assert_eq!(get_mipmap_count("test.tga$nomips.png", 256, 64), Ok(1));
+    assert_eq!(get_mipmap_count("test.tga$mips=4.png", 256, 64), Ok(4));
+    assert_eq!(get_mipmap_count("test.tga$mips=20.png", 256, 64), Ok(9));
+    assert_eq!(get_mipmap_count("test.tga$minmip=4.png", 256, 64), Ok(5));
+    assert_eq!(get_mipmap_count("test.tga$mips=3$minmip=4.png", 256, 64), Ok(3));
+    assert_eq!(get_mipmap_count("test.tga$minmip=512.png", 256, 64), Ok(1));
+    assert!(get_mipmap_count("test.tga$mips=0.png", 256, 64).is_err());
+    assert!(get_mipmap_count("test.tga$mips=abc.png", 256, 64).is_err());
+}
+
+#[test]
+fn test_parse_token() {
+    assert_eq!(utils::parse_token("test.tga$mips=4.png", "mips"), Ok(Some(4)));
+    assert_eq!(utils::parse_token("test.tga$mips=4", "mips"), Ok(Some(4)));
+    assert_eq!(utils::parse_token::<u32>("test.png", "mips"), Ok(None));
+
+    // Extensionless names keep the decimals of their last token
+    assert_eq!(
+        utils::parse_token("rock_h.tga$normals$strength=2.5", "strength"),
+        Ok(Some(2.5))
+    );
+    assert_eq!(
+        utils::parse_token("glyph.tga$sdf$spread=1.5", "spread"),
+        Ok(Some(1.5))
+    );
+    assert_eq!(
+        utils::parse_token("glyph.tga$spread=1.5.png", "spread"),
+        Ok(Some(1.5))
+    );
+    assert!(utils::has_token("rock_h.tga$strength=2.5$normals", "normals"));
+
+    assert_eq!(
+        utils::split_extension("name.tga$bc7.png"),
+        ("name.tga$bc7", Some("png"))
+    );
+    assert_eq!(utils::split_extension("name.tga$bc7"), ("name.tga$bc7", None));
+    assert_eq!(
+        utils::split_extension("name.tga$scale=0.5"),
+        ("name.tga$scale=0.5", None)
+    );
+    assert_eq!(
+        get_bimage_output_path(
+            Path::new("rock_h.tga$normals$strength=2.5"),
+            "rock_h.tga$normals$strength=2.5"
+        ),
+        PathBuf::from("rock_h.tga$normals$strength=2.5")
+    );
+}
+
+#[test]
+fn test_mip_count_header() {
+    for (file_name, expected_mip_count) in [
+        ("test.tga$alpha.png", 7),
+        ("test.tga$alpha$nomips.png", 1),
+        ("test.tga$alpha$mips=3.png", 3),
+        ("test.tga$alpha$minmip=8.png", 3)
+    ] {
+        let bim_bytes = convert_to_bimage(
+            RgbaImage::new(64, 32),
+            HashMap::new(),
+            file_name.into(),
+            "test".into(),
+            TextureFormat::FmtAlpha,
+            false
+        )
+        .unwrap();
+
+        // Count the mip entries, which are followed by the data of every mip
+        let mut entry_count = 0;
+        let mut texture_size = 0;
+
+        while BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * entry_count + texture_size < bim_bytes.len() {
+            let entry_start = BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * entry_count;
+            let bim_mip = BIMMipMap::from_bytes(&bim_bytes[entry_start..entry_start + BIM_MIP_MAP_SIZE]);
+            assert_eq!(bim_mip.mip_level, entry_count as i32);
+
+            texture_size += bim_mip.compressed_size as usize;
+            entry_count += 1;
+        }
+
+        let bim_header = BIMHeader::from_bytes(&bim_bytes).unwrap();
+        assert_eq!(bim_header.mip_count as usize, entry_count, "{}", file_name);
+        assert_eq!(bim_header.mip_count, expected_mip_count, "{}", file_name);
+        assert_eq!(bim_header.streamdb_mip_count, 0);
+        assert_eq!(
+            BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * entry_count + texture_size,
+            bim_bytes.len()
+        );
+    }
+}
+
+#[test]
+fn test_apply_size_policy() {
+    let img = RgbaImage::new(100, 24);
+
+    let (padded, warning) = apply_size_policy(img.clone(), "test.png").unwrap();
+    assert_eq!(padded.dimensions(), (100, 24));
+    assert!(warning.is_some());
+
+    let (resized, _) = apply_size_policy(img.clone(), "test.tga$npot=pow2.png").unwrap();
+    assert_eq!(resized.dimensions(), (128, 32));
+
+    let (cropped, _) = apply_size_policy(img.clone(), "test.tga$npot=crop.png").unwrap();
+    assert_eq!(cropped.dimensions(), (64, 16));
+
+    assert!(apply_size_policy(img.clone(), "test.tga$npot=reject.png").is_err());
+    assert!(apply_size_policy(img, "test.tga$npot=stretch.png").is_err());
+
+    // Power of two textures are left alone
+    let (img, warning) = apply_size_policy(RgbaImage::new(64, 2), "test.tga$npot=reject.png").unwrap();
+    assert_eq!(img.dimensions(), (64, 2));
+    assert!(warning.is_none());
+
+    // Custom mips follow their texture, wrong sizes are left for the mip chain check
+    let custom_mips = HashMap::from([(1, RgbaImage::new(50, 12)), (2, RgbaImage::new(7, 7))]);
+    let fitted = fit_custom_mips(
+        custom_mips.clone(),
+        "test.tga$npot=pow2.png",
+        (100, 24),
+        (128, 32)
+    );
+    assert_eq!(fitted[&1].dimensions(), (64, 16));
+    assert_eq!(fitted[&2].dimensions(), (7, 7));
+
+    let fitted = fit_custom_mips(custom_mips, "test.tga$npot=crop.png", (100, 24), (64, 16));
+    assert_eq!(fitted[&1].dimensions(), (32, 8));
+
+    // Cubemap faces and slices all follow the policy, with a single warning
+    let mut output = String::new();
+    let slices = apply_size_policy_to_all(
+        vec![RgbaImage::new(100, 24); 3],
+        "test.tga$npot=pow2$slice0.png",
+        &mut output
+    )
+    .unwrap();
+    assert!(slices.iter().all(|slice| slice.dimensions() == (128, 32)));
+    assert_eq!(output.matches("WARNING").count(), 1);
+
+    assert!(apply_size_policy_to_all(
+        vec![RgbaImage::new(100, 24); 6],
+        "sky.tga$npot=reject$face0.png",
+        &mut output
+    )
+    .is_err());
+}
+
+#[test]
+fn test_load_image_formats() {
+    let png_img = load_image(Path::new("./test/test.png"), &mut String::new()).unwrap();
+    let dir = TempDir::new("load_image_formats");
+
+    // Lossless formats should decode to the same pixels
+    for format in [ImageFormat::Tga, ImageFormat::Bmp, ImageFormat::Tiff] {
+        let path = dir.join(format!("test.{}", format.extensions_str()[0]));
+        png_img.save_with_format(&path, format).unwrap();
+        assert_eq!(load_image(&path, &mut String::new()).unwrap(), png_img);
+    }
+
+    // TGA is detected even if the extension says otherwise
+    let path = dir.join("test.tga$bc7.png");
+    png_img.save_with_format(&path, ImageFormat::Tga).unwrap();
+    assert_eq!(load_image(&path, &mut String::new()).unwrap(), png_img);
+
+    // HDR values above 1.0 are clipped, with a warning
+    let path = dir.join("test.hdr");
+    image::Rgb32FImage::from_pixel(2, 2, image::Rgb([4.0, 0.5, 0.0]))
+        .save_with_format(&path, ImageFormat::Hdr)
+        .unwrap();
+
+    let mut output = String::new();
+    let hdr_img = load_image(&path, &mut output).unwrap();
+    assert_eq!(hdr_img.get_pixel(0, 0).0[0], 255);
+    assert!(output.contains("values above 1.0"));
+}
+
+#[test]
+fn test_convert_dds_to_bimage() {
+    // 8x8 DXT1 DDS with 4 mips
+    let mut dds_bytes = vec![0_u8; 128];
+    dds_bytes[0..4].copy_from_slice(&dds::DDS_MAGIC);
+    dds_bytes[4..8].copy_from_slice(&124_u32.to_le_bytes());
+    dds_bytes[8..12].copy_from_slice(&0x2100F_u32.to_le_bytes());
+    dds_bytes[12..16].copy_from_slice(&8_u32.to_le_bytes());
+    dds_bytes[16..20].copy_from_slice(&8_u32.to_le_bytes());
+    dds_bytes[28..32].copy_from_slice(&4_u32.to_le_bytes());
+    dds_bytes[76..80].copy_from_slice(&32_u32.to_le_bytes());
+    dds_bytes[80..84].copy_from_slice(&4_u32.to_le_bytes());
+    dds_bytes[84..88].copy_from_slice(b"DXT1");
+    dds_bytes.extend((0..32 + 8 + 8 + 8).map(|i| i as u8));
+
+    let dds = DdsTexture::from_bytes(&dds_bytes).unwrap();
+    assert_eq!(dds.format, TextureFormat::FmtBc1);
+    assert_eq!(dds.mip_count, 4);
+
+    let mut output = String::new();
+    let bim_bytes = convert_dds_to_bimage(dds, "test.dds".into(), "test".into(), false, &mut output).unwrap();
+
+    // Header, legacy DXT1 albedo is sRGB like the converted ones
+    assert_eq!(bim_bytes[24..28], 4_u32.to_le_bytes());
+    assert_eq!(
+        bim_bytes[41..45],
+        (TextureFormat::FmtBc1Srgb as u32).to_le_bytes()
+    );
+
+    // Last mip entry is 1x1, 8 bytes, after 48 bytes of data
+    let last_mip = &bim_bytes[63 + 36 * 3..63 + 36 * 4];
+    assert_eq!(last_mip[8..16], [1, 0, 0, 0, 1, 0, 0, 0]);
+    assert_eq!(last_mip[28..36], [8, 0, 0, 0, 48, 0, 0, 0]);
+
+    // Blocks are copied as is
+    assert_eq!(bim_bytes[63 + 36 * 4..], dds_bytes[128..]);
+    assert!(output.is_empty());
+
+    // Other material kinds stay linear, and encoding tokens are reported as ignored
+    let dds = DdsTexture::from_bytes(&dds_bytes).unwrap();
+    let bim_bytes = convert_dds_to_bimage(
+        dds,
+        "test_s.tga$nomips$bc7.dds".into(),
+        "test_s".into(),
+        false,
+        &mut output
+    )
+    .unwrap();
+    assert_eq!(bim_bytes[41..45], (TextureFormat::FmtBc1 as u32).to_le_bytes());
+    assert!(output.contains("Ignoring $nomips, $bc7"));
+}
+
+#[test]
+fn test_convert_bimage_to_dds() {
+    let src_dds = DdsTexture {
+        width: 8,
+        height: 4,
+        depth: 1,
+        mip_count: 4,
+        texture_type: TextureType::Tt2d,
+        format: TextureFormat::FmtBc7Srgb,
+        legacy_header: false,
+        data: (0..32 + 16 + 16 + 16).map(|i| i as u8).collect()
+    };
+
+    // Round trip through bimage, compressed with kraken
+    let bim_bytes = convert_dds_to_bimage(
+        DdsTexture::from_bytes(&src_dds.to_bytes()).unwrap(),
+        "test.dds".into(),
+        "test".into(),
+        true,
+        &mut String::new()
+    )
+    .unwrap();
+    let dds_bytes = convert_bimage_to_dds(&kraken_decompress(&bim_bytes).unwrap()).unwrap();
+    let dds = DdsTexture::from_bytes(&dds_bytes).unwrap();
+
+    assert_eq!((dds.width, dds.height, dds.mip_count), (8, 4, 4));
+    assert_eq!(dds.format, TextureFormat::FmtBc7Srgb);
+
+    // Blocks are kept as is
+    assert_eq!(dds.data, src_dds.data);
+
+    // Corrupt sizes are reported instead of trusted
+    let mut corrupt_bytes = bim_bytes.clone();
+    corrupt_bytes[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(kraken_decompress(&corrupt_bytes).is_err());
+
+    let mut corrupt_bytes = kraken_decompress(&bim_bytes).unwrap();
+    let entry_start = BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE;
+    let mut bim_mip = BIMMipMap::from_bytes(&corrupt_bytes[entry_start..entry_start + BIM_MIP_MAP_SIZE]);
+    bim_mip.decompressed_size = u32::MAX;
+    corrupt_bytes[entry_start..entry_start + BIM_MIP_MAP_SIZE].copy_from_slice(&bim_mip.to_bytes());
+    assert!(convert_bimage_to_dds(&corrupt_bytes).is_err());
+}
+
+#[test]
+fn test_convert_bimage_to_ktx2() {
+    let src_img = RgbaImage::from_pixel(8, 8, image::Rgba([0, 0, 0, 10]));
+    let bim_bytes = convert_to_bimage(
+        src_img,
+        HashMap::new(),
+        "test.tga$bc7.png".into(),
+        "test".into(),
+        TextureFormat::FmtBc7,
+        false
+    )
+    .unwrap();
+
+    let ktx2_bytes = convert_bimage_to_ktx2(&bim_bytes).unwrap();
+    let read_u32 = |offset: usize| u32::from_le_bytes(ktx2_bytes[offset..offset + 4].try_into().unwrap());
+    let read_u64 = |offset: usize| u64::from_le_bytes(ktx2_bytes[offset..offset + 8].try_into().unwrap());
+
+    // Header
+    assert_eq!(ktx2_bytes[0..12], ktx2::KTX2_IDENTIFIER);
+    assert_eq!(read_u32(12), VkFormat::Bc7Unorm as u32);
+    assert_eq!((read_u32(20), read_u32(24), read_u32(40)), (8, 8, 4));
+
+    // Data format descriptor follows the level index
+    assert_eq!(read_u32(48), 80 + 24 * 4);
+    assert_eq!(read_u32(52), 44);
+    assert_eq!(read_u32(176), 44);
+
+    // Levels are stored from the smallest to the largest, with the same data as the bimage
+    let mip_0_start = read_u64(80) as usize;
+    let mip_3_start = read_u64(80 + 24 * 3) as usize;
+    assert_eq!(read_u64(88), 64);
+    assert_eq!(mip_3_start % 16, 0);
+    assert!(mip_3_start < mip_0_start);
+    assert_eq!(
+        ktx2_bytes[mip_0_start..mip_0_start + 64],
+        bim_bytes[63 + 36 * 4..63 + 36 * 4 + 64]
+    );
+    assert_eq!(ktx2_bytes.len(), mip_0_start + 64);
+}
+
+#[test]
+fn test_split_cube_cross() {
+    // Fill every cell of the cross with its own color
+    let cross = |columns: u32, rows: u32| {
+        RgbaImage::from_fn(columns * 4, rows * 4, |x, y| {
+            image::Rgba([(x / 4) as u8, (y / 4) as u8, (y % 4) as u8, 255])
+        })
+    };
+
+    // Horizontal cross
+    let faces = split_cube_cross(&cross(4, 3)).unwrap();
+    let cells: Vec<(u8, u8)> = faces
+        .iter()
+        .map(|face| (face.get_pixel(0, 0)[0], face.get_pixel(0, 0)[1]))
+        .collect();
+    assert_eq!(cells, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)]);
+    assert!(faces.iter().all(|face| face.dimensions() == (4, 4)));
+
+    // Vertical cross, -Z is upside down
+    let faces = split_cube_cross(&cross(3, 4)).unwrap();
+    assert_eq!(faces[4].get_pixel(0, 0).0, [1, 1, 0, 255]);
+    assert_eq!(faces[5].get_pixel(0, 0).0, [1, 3, 3, 255]);
+
+    assert!(split_cube_cross(&cross(2, 1)).is_err());
+}
+
+#[test]
+fn test_convert_cubemap_to_bimage() {
+    let faces = (0..6)
+        .map(|face| RgbaImage::from_pixel(8, 8, image::Rgba([face * 40, 0, 0, 255])))
+        .collect();
+    let bim_bytes = convert_cubemap_to_bimage(
+        faces,
+        "env.tga$cube.png".into(),
+        "env".into(),
+        TextureFormat::FmtBc1Srgb,
+        false
+    )
+    .unwrap();
+
+    // Header
+    let bim_header = BIMHeader::from_bytes(&bim_bytes).unwrap();
+    assert_eq!(bim_header.texture_type, TextureType::TtCubic as u32);
+    assert_eq!(bim_header.bool_is_environment_map, 1);
+    assert_eq!(bim_header.mip_count, 4);
+
+    // Every face has its whole mip chain, one after another
+    for entry in 0..24 {
+        let entry_start = BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * entry;
+        let bim_mip = BIMMipMap::from_bytes(&bim_bytes[entry_start..entry_start + BIM_MIP_MAP_SIZE]);
+        assert_eq!(bim_mip.mip_level, entry as i32 % 4);
+        assert_eq!(bim_mip.mip_dest_z, entry as i32 / 4);
+    }
+
+    // KTX2 levels hold that mip of every face
+    let ktx2_bytes = convert_bimage_to_ktx2(&bim_bytes).unwrap();
+    let read_u32 = |offset: usize| u32::from_le_bytes(ktx2_bytes[offset..offset + 4].try_into().unwrap());
+    let read_u64 = |offset: usize| u64::from_le_bytes(ktx2_bytes[offset..offset + 8].try_into().unwrap());
+    assert_eq!(
+        (read_u32(28), read_u32(32), read_u32(36), read_u32(40)),
+        (0, 0, 6, 4)
+    );
+
+    let (mip_0_start, texture_start) = (read_u64(80) as usize, BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * 24);
+    assert_eq!(read_u64(88), 6 * 32);
+    assert_eq!(
+        ktx2_bytes[mip_0_start + 32..mip_0_start + 64],
+        bim_bytes[texture_start + 56..texture_start + 88]
+    );
+
+    // DDS cubemaps keep every face's mip chain together, like bimages
+    let dds_bytes = convert_bimage_to_dds(&bim_bytes).unwrap();
+    assert_eq!(dds_bytes[112..116], 0xFE00_u32.to_le_bytes());
+    assert_eq!(dds_bytes[136..144], [4, 0, 0, 0, 1, 0, 0, 0]);
+    assert_eq!(dds_bytes[148..], bim_bytes[texture_start..]);
+
+    // Faces must be square
+    let faces = (0..6).map(|_| RgbaImage::new(8, 4)).collect();
+    assert!(convert_cubemap_to_bimage(
+        faces,
+        "env.png".into(),
+        "env".into(),
+        TextureFormat::FmtBc1Srgb,
+        false
+    )
+    .is_err());
+}
+
+#[test]
+fn test_cube_face_paths() {
+    let path = Path::new("dir/sky.tga$face0.png");
+    assert_eq!(utils::cube_face_index(path), Some(0));
+    assert_eq!(utils::cube_face_index(Path::new("sky$face6.png")), None);
+    assert_eq!(
+        utils::cube_face_path(path, 3),
+        Some(PathBuf::from("dir/sky.tga$face3.png"))
+    );
+    assert_eq!(
+        utils::strip_last_token(path),
+        Some(PathBuf::from("dir/sky.tga.png"))
+    );
+
+    let path = Path::new("dir/sky.tga$face0");
+    assert_eq!(utils::cube_face_index(path), Some(0));
+    assert_eq!(
+        utils::cube_face_path(path, 3),
+        Some(PathBuf::from("dir/sky.tga$face3"))
+    );
+    assert_eq!(utils::strip_last_token(path), Some(PathBuf::from("dir/sky.tga")));
+}
+
+#[test]
+fn test_equirect_to_cube_faces() {
+    // Brightness goes from the bottom to the top, red from the front (+Z) to the back
+    let panorama = RgbaImage::from_fn(64, 32, |x, y| {
+        let u = (x as f32 + 0.5) / 64.0;
+        let red = 127.5 + 127.5 * (2.0 * std::f32::consts::PI * (u - 0.5)).cos();
+        image::Rgba([red as u8, 255 - (y * 8) as u8, 0, 255])
+    });
+
+    for filter in [cubemap::SampleFilter::Bilinear, cubemap::SampleFilter::Bicubic] {
+        let faces = cubemap::equirect_to_cube_faces(&panorama, 8, filter);
+        assert_eq!(faces.len(), 6);
+
+        // +Y is the top of the panorama, -Y the bottom, +Z the front and -Z the back
+        assert!(faces[2].get_pixel(4, 4)[1] > 230);
+        assert!(faces[3].get_pixel(4, 4)[1] < 25);
+        assert!(faces[4].get_pixel(4, 4)[0] > 230);
+        assert!(faces[5].get_pixel(4, 4)[0] < 25);
+
+        // Texels on both sides of an edge between faces are close, including across the panorama's seam
+        let close =
+            |a: &image::Rgba<u8>, b: &image::Rgba<u8>| a.0.iter().zip(b.0).all(|(a, b)| a.abs_diff(b) <= 24);
+
+        for y in 0..8 {
+            assert!(close(faces[0].get_pixel(7, y), faces[5].get_pixel(0, y)));
+            assert!(close(faces[5].get_pixel(7, y), faces[1].get_pixel(0, y)));
+            assert!(close(faces[4].get_pixel(7, y), faces[0].get_pixel(0, y)));
+        }
+    }
+
+    assert_eq!("bicubic".parse(), Ok(cubemap::SampleFilter::Bicubic));
+    assert!("nearest".parse::<cubemap::SampleFilter>().is_err());
+}
+
+#[test]
+fn test_cube_level_sample() {
+    // Red +X, green +Z and black everywhere else
+    let faces: Vec<RgbaImage> = (0..6)
+        .map(|face| match face {
+            0 => RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255])),
+            4 => RgbaImage::from_pixel(4, 4, image::Rgba([0, 255, 0, 255])),
+            _ => RgbaImage::from_pixel(4, 4, image::Rgba([0, 0, 0, 255]))
+        })
+        .collect();
+    let level = cubemap::CubeLevel::from_faces(&faces, false);
+
+    // Face centers are their own color
+    assert_eq!(level.sample([1.0, 0.0, 0.0]), [1.0, 0.0, 0.0, 1.0]);
+
+    // The edge between +X and +Z blends both faces, the same from either side
+    let close = |a: [f32; 4], b: [f32; 4]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 0.02);
+    let x_side = level.sample([1.0, 0.0, 0.999]);
+    let z_side = level.sample([0.999, 0.0, 1.0]);
+    assert!(close(x_side, [0.5, 0.5, 0.0, 1.0]));
+    assert!(close(x_side, z_side));
+
+    // Also at a corner, between +X, +Z and black +Y
+    let corner = level.sample([1.0, 0.999, 0.999]);
+    assert!(corner[0] > 0.2 && corner[1] > 0.2 && corner[0] + corner[1] < 0.9);
+}
+
+#[test]
+fn test_prefilter_cube_faces() {
+    // A constant environment stays constant at any roughness
+    let faces: Vec<RgbaImage> = (0..6)
+        .map(|_| RgbaImage::from_pixel(8, 8, image::Rgba([100, 150, 200, 255])))
+        .collect();
+    let mips = cubemap::prefilter_cube_faces(&faces, 4, 32, true);
+
+    for face_mips in &mips {
+        assert_eq!(face_mips.len(), 4);
+
+        for (level, mip) in face_mips.iter().enumerate() {
+            assert_eq!(mip.len(), 4 * (8 >> level) * (8 >> level));
+            assert!(mip.chunks(4).all(|texel| texel == [100, 150, 200, 255]));
+        }
+    }
+
+    // Only +X is lit, and it spreads to its neighbours as roughness increases
+    let faces: Vec<RgbaImage> = (0..6)
+        .map(|face| match face {
+            0 => RgbaImage::from_pixel(8, 8, image::Rgba([255, 255, 255, 255])),
+            _ => RgbaImage::from_pixel(8, 8, image::Rgba([0, 0, 0, 255]))
+        })
+        .collect();
+
+    let mips = cubemap::prefilter_cube_faces(&faces, 4, 32, false);
+    assert_eq!(mips, cubemap::prefilter_cube_faces(&faces, 4, 32, false));
+
+    // Mip 0 is the source itself
+    assert_eq!(mips[0][0], faces[0].as_raw().clone());
+    assert_eq!(mips[4][0], faces[4].as_raw().clone());
+
+    // Snapshot of the center texels of +X and +Z, at every level
+    let center = |mip: &Vec<u8>, size: usize| mip[4 * (size / 2 * size + size / 2)];
+    let snapshot: Vec<(u8, u8)> = (1..4)
+        .map(|level| {
+            (
+                center(&mips[0][level], 8 >> level),
+                center(&mips[4][level], 8 >> level)
+            )
+        })
+        .collect();
+    assert_eq!(snapshot, [(238, 8), (142, 54), (134, 39)]);
+}
+
+#[test]
+fn test_convert_slices_to_bimage() {
+    let slices = || {
+        (0..3)
+            .map(|slice| RgbaImage::from_pixel(8, 4, image::Rgba([slice * 80, 0, 0, 255])))
+            .collect()
+    };
+
+    // Array, every slice has its whole mip chain
+    let bim_bytes = convert_slices_to_bimage(
+        slices(),
+        "noise.tga$slice0.png".into(),
+        "noise".into(),
+        TextureFormat::FmtBc1Srgb,
+        false
+    )
+    .unwrap();
+
+    let bim_header = BIMHeader::from_bytes(&bim_bytes).unwrap();
+    assert_eq!(bim_header.texture_type, TextureType::Tt2d as u32);
+    assert_eq!(bim_header.depth, 3);
+    assert_eq!(bim_header.mip_count, 4);
+
+    for entry in 0..12 {
+        let entry_start = BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * entry;
+        let bim_mip = BIMMipMap::from_bytes(&bim_bytes[entry_start..entry_start + BIM_MIP_MAP_SIZE]);
+        assert_eq!(bim_mip.mip_level, entry as i32 % 4);
+        assert_eq!(bim_mip.mip_dest_z, entry as i32 / 4);
+    }
+
+    // Exported as arrays of 3 layers
+    let dds_bytes = convert_bimage_to_dds(&bim_bytes).unwrap();
+    assert_eq!(dds_bytes[140..144], 3_u32.to_le_bytes());
+    assert_eq!(dds_bytes.len(), 148 + 3 * 40);
+
+    let ktx2_bytes = convert_bimage_to_ktx2(&bim_bytes).unwrap();
+    assert_eq!(ktx2_bytes[28..40], [0, 0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0]);
+
+    // Volume, without mips
+    let bim_bytes = convert_slices_to_bimage(
+        slices(),
+        "noise.tga$volume$slice0.png".into(),
+        "noise".into(),
+        TextureFormat::FmtBc1Srgb,
+        false
+    )
+    .unwrap();
+
+    let bim_header = BIMHeader::from_bytes(&bim_bytes).unwrap();
+    assert_eq!(bim_header.texture_type, TextureType::Tt3d as u32);
+    assert_eq!(bim_header.depth, 3);
+    assert_eq!(bim_header.mip_count, 1);
+    assert_eq!(bim_bytes.len(), BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * 3 + 3 * 16);
+
+    // Exported as volumes of 3 slices
+    let dds_bytes = convert_bimage_to_dds(&bim_bytes).unwrap();
+    assert_eq!(dds_bytes[24..28], 3_u32.to_le_bytes());
+    assert_eq!(dds_bytes[132..136], 4_u32.to_le_bytes());
+    assert_eq!(
+        dds_bytes[148..],
+        bim_bytes[BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * 3..]
+    );
+
+    let ktx2_bytes = convert_bimage_to_ktx2(&bim_bytes).unwrap();
+    assert_eq!(ktx2_bytes[28..40], [3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]);
+
+    // Mip tokens can't be used with volumes
+    assert!(convert_slices_to_bimage(
+        slices(),
+        "noise.tga$volume$mips=2$slice0.png".into(),
+        "noise".into(),
+        TextureFormat::FmtBc1Srgb,
+        false
+    )
+    .is_err());
+
+    // Slices must all be the same size
+    let mut mismatched: Vec<RgbaImage> = slices();
+    mismatched[1] = RgbaImage::new(4, 4);
+    assert!(convert_slices_to_bimage(
+        mismatched,
+        "noise.png".into(),
+        "noise".into(),
+        TextureFormat::FmtBc1Srgb,
+        false
+    )
+    .is_err());
+}
+
+#[test]
+fn test_slice_paths() {
+    let path = Path::new("dir/noise.tga$volume$slice0.png");
+    assert_eq!(utils::slice_index(path), Some(0));
+    assert_eq!(utils::slice_index(Path::new("noise.png")), None);
+    assert_eq!(
+        utils::slice_path(path, 12),
+        Some(PathBuf::from("dir/noise.tga$volume$slice12.png"))
+    );
+
+    let path = Path::new("dir/noise.tga$volume$slice0");
+    assert_eq!(utils::slice_index(path), Some(0));
+    assert_eq!(
+        utils::slice_path(path, 12),
+        Some(PathBuf::from("dir/noise.tga$volume$slice12"))
+    );
+}
+
+#[test]
+fn test_build_atlas() {
+    // 5 frames of 2x2, each filled with its index
+    let frames: Vec<RgbaImage> = (0..5)
+        .map(|i| RgbaImage::from_pixel(2, 2, image::Rgba([i, 0, 0, 255])))
+        .collect();
+
+    let (atlas_img, layout) = atlas::build_atlas(&frames, None, 1).unwrap();
+    assert_eq!((layout.columns, layout.rows, layout.frame_count), (3, 2, 5));
+    assert_eq!(atlas_img.dimensions(), (12, 8));
+
+    // Padding repeats the frame's edge, and unused cells are transparent
+    assert_eq!(atlas_img.get_pixel(4, 0).0, [1, 0, 0, 255]);
+    assert_eq!(atlas_img.get_pixel(7, 3).0, [1, 0, 0, 255]);
+    assert_eq!(atlas_img.get_pixel(4, 4).0, [4, 0, 0, 255]);
+    assert_eq!(atlas_img.get_pixel(11, 7).0, [0, 0, 0, 0]);
+
+    let (atlas_img, layout) = atlas::build_atlas(&frames, Some(5), 0).unwrap();
+    assert_eq!((layout.columns, layout.rows), (5, 1));
+    assert_eq!(atlas_img.dimensions(), (10, 2));
+    assert!(layout.to_json().contains("\"frames\": 5"));
+
+    assert!(atlas::build_atlas(&frames, Some(0), 0).is_err());
+    assert!(atlas::build_atlas(&[RgbaImage::new(2, 2), RgbaImage::new(4, 4)], None, 0).is_err());
+}
+
+#[test]
+fn test_convert_frames_dir() {
+    let temp_dir = TempDir::new("convert_frames_dir");
+    let dir = temp_dir.join("frames.tga$mtlkind=particle$padding=2.frames");
+    fs::create_dir(&dir).unwrap();
+
+    for i in 0..4 {
+        RgbaImage::from_pixel(4, 4, image::Rgba([i * 60, 0, 0, 255]))
+            .save(dir.join(format!("frame{}.png", i)))
+            .unwrap();
+    }
+
+    fs::write(dir.join("notes.txt"), "not a frame").unwrap();
+
+    let file_name = dir.file_name().unwrap().to_str().unwrap();
+    let mut output = String::new();
+    let frames = load_frames(&dir, &mut String::new()).unwrap();
+    let (bim_bytes, layout) =
+        convert_frames(frames, &dir, file_name, "frames".into(), false, &mut output).unwrap();
+
+    assert_eq!((layout.columns, layout.rows, layout.frame_count), (2, 2, 4));
+
+    let bim_header = BIMHeader::from_bytes(&bim_bytes).unwrap();
+    assert_eq!((bim_header.pixel_width, bim_header.pixel_height), (16, 16));
+    assert_eq!(bim_header.atlas_padding, 2);
+    assert_eq!(
+        bim_header.texture_material_kind,
+        TextureMaterialKind::TmkParticle as u32
+    );
+}
+
+#[test]
+fn test_pack_frames() {
+    let frames = || {
+        (0..8)
+            .map(|i| RgbaImage::from_pixel(16, 8, image::Rgba([i, 0, 0, 255])))
+            .collect()
+    };
+    let mut output = String::new();
+
+    // Every other frame
+    let (atlas_img, layout) = pack_frames(frames(), "fx$stride=2.png", &mut output).unwrap();
+    assert_eq!((layout.columns, layout.rows, layout.frame_count), (2, 2, 4));
+    assert_eq!(atlas_img.get_pixel(16, 0).0, [2, 0, 0, 255]);
+
+    // Frames are shrunk to fit, keeping their aspect ratio
+    let (atlas_img, layout) = pack_frames(frames(), "fx$maxatlas=32$padding=1.png", &mut output).unwrap();
+    assert_eq!((layout.frame_width, layout.frame_height), (8, 4));
+    assert_eq!(atlas_img.dimensions(), (30, 18));
+    assert!(output.contains("shrunk from 16x8 to 8x4"));
+
+    assert!(pack_frames(frames(), "fx$maxatlas=4$padding=1.png", &mut output).is_err());
+    assert!(pack_frames(frames(), "fx$stride=0.png", &mut output).is_err());
+}
+
+#[test]
+fn test_load_animation_frames() {
+    use image::codecs::gif::GifEncoder;
+
+    let dir = TempDir::new("load_animation_frames");
+
+    // Animated GIF
+    let path = dir.join("fx.gif");
+    let mut encoder = GifEncoder::new(File::create(&path).unwrap());
+
+    for i in 0..3 {
+        let frame = RgbaImage::from_pixel(4, 4, image::Rgba([i * 100, 0, 0, 255]));
+        encoder.encode_frame(Frame::new(frame)).unwrap();
+    }
+
+    drop(encoder);
+
+    let frames = load_animation_frames(&path).unwrap().unwrap();
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[2].get_pixel(0, 0).0, [200, 0, 0, 255]);
+
+    // Still images aren't animations
+    assert!(load_animation_frames(Path::new("./test/test.png"))
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_generate_sdf() {
+    // White disc of radius 32 on black, centered in a 128x128 image
+    let glyph = RgbaImage::from_fn(128, 128, |x, y| {
+        let (dx, dy) = (x as f32 + 0.5 - 64.0, y as f32 + 0.5 - 64.0);
+
+        match (dx * dx + dy * dy).sqrt() < 32.0 {
+            true => image::Rgba([255, 255, 255, 255]),
+            false => image::Rgba([0, 0, 0, 255])
+        }
+    });
+
+    let sdf_img = sdf::generate_sdf(&glyph, 2.0, 16, 16);
+    assert_eq!(sdf_img.dimensions(), (16, 16));
+
+    // Deep inside and far outside saturate, and the edge is around half
+    assert_eq!(sdf_img.get_pixel(8, 8).0, [255; 4]);
+    assert_eq!(sdf_img.get_pixel(0, 0).0, [0; 4]);
+
+    // Just inside the edge is a bit above half
+    let edge = sdf_img.get_pixel(8, 4)[3];
+    assert!((145..=170).contains(&edge), "{}", edge);
+
+    // The distance field is symmetric
+    assert_eq!(sdf_img.get_pixel(4, 8), sdf_img.get_pixel(8, 4));
+
+    // Converted as alpha, at an eighth of the size by default
+    let mut output = String::new();
+    let sdf_img = generate_sdf_image(glyph, "glyph.tga$sdf.png", &mut output).unwrap();
+    assert_eq!(sdf_img.dimensions(), (16, 16));
+    assert_eq!(
+        get_target_format("glyph.tga$sdf.png", "glyph"),
+        TextureFormat::FmtAlpha
+    );
+
+    // Only the exact token turns images into a distance field
+    assert!(utils::has_token("glyph.tga$sdf$sdfsize=8.png", "sdf"));
+    assert!(!utils::has_token("glyph.tga$sdfsize=8.png", "sdf"));
+    assert_eq!(
+        get_target_format("glyph.tga$sdfsize=8.png", "glyph"),
+        TextureFormat::FmtBc1Srgb
+    );
+}
+
+#[test]
+fn test_font_charset() {
+    let chars = font::parse_charset("65-67,32,66").unwrap();
+    assert_eq!(chars, vec![' ', 'A', 'B', 'C']);
+
+    assert!(font::parse_charset("67-65").is_err());
+    assert!(font::parse_charset("a-z").is_err());
+
+    // Fonts are converted as alpha font textures
+    assert!(utils::is_font_file("64_df.tga$nomips.TTF"));
+    assert!(!utils::is_font_file("64_df.tga"));
+    assert_eq!(
+        get_target_format("64_df.tga$nomips.otf", "64_df"),
+        TextureFormat::FmtAlpha
+    );
+    assert_eq!(
+        TextureMaterialKind::from_filename(
+            "64_df.tga$nomips.otf".into(),
+            "64_df".into(),
+            TextureFormat::FmtAlpha
+        ),
+        TextureMaterialKind::TmkFont
+    );
+}
+
+#[test]
+fn test_build_font_atlas() {
+    let file_path = Path::new("./test/DejaVuSansMono.tga$chars=32,65-70$glyphsize=16$spread=2$nomips.ttf");
+    let file_name = file_path.file_name().unwrap().to_str().unwrap();
+    let mut output = String::new();
+
+    let font_atlas = build_font_atlas(file_path, file_name, &mut output).unwrap();
+    assert_eq!(font_atlas.image.dimensions(), (64, 64));
+    assert_eq!((font_atlas.size, font_atlas.spread), (16, 2.0));
+    assert!(output.contains("Rasterized 7 glyphs into a 64x64 distance field atlas"));
+
+    // The space only advances the pen, every glyph of a monospaced font advances it the same
+    let space = &font_atlas.glyphs[0];
+    assert_eq!((space.character, space.width, space.height), (' ', 0, 0));
+    assert!(font_atlas
+        .glyphs
+        .iter()
+        .all(|glyph| glyph.advance == space.advance && glyph.advance > 0.0));
+
+    // Glyphs are padded by the spread, don't overlap and sit above the baseline
+    let glyphs = &font_atlas.glyphs[1..];
+
+    for (i, glyph) in glyphs.iter().enumerate() {
+        assert!(glyph.x + glyph.width <= 64 && glyph.y + glyph.height <= 64);
+        assert!(glyph.y_offset < -2.0);
+
+        for other in &glyphs[i + 1..] {
+            assert!(
+                glyph.x + glyph.width <= other.x
+                    || other.x + other.width <= glyph.x
+                    || glyph.y + glyph.height <= other.y
+                    || other.y + other.height <= glyph.y
+            );
+        }
+    }
+
+    // Strokes are inside the edge, and the padded corners fully outside
+    let a = &font_atlas.glyphs[1];
+    let a_img = image::imageops::crop_imm(&font_atlas.image, a.x, a.y, a.width, a.height).to_image();
+    assert_eq!(a.character, 'A');
+    assert!(a_img.pixels().any(|pixel| pixel[3] > 128));
+    assert_eq!(a_img.get_pixel(0, 0)[3], 0);
+
+    // Sidecar, with code points
+    let (_, glyphs_json) =
+        convert_font_file(file_path, file_name, "DejaVuSansMono".into(), false, &mut output).unwrap();
+    assert!(glyphs_json.contains("\"size\": 16,"));
+    assert!(glyphs_json.contains("{ \"char\": 65, "));
+    assert_eq!(glyphs_json.matches("\"char\"").count(), 7);
+}
+
+#[test]
+fn test_rasterize_svg() {
+    // Opaque red left half, transparent right half
+    let svg_data = br##"<svg xmlns="http://www.w3.org/2000/svg" width="32" height="16">
+        <rect x="0" y="0" width="16" height="16" fill="#ff0000"/>
+    </svg>"##;
+
+    let dir = TempDir::new("rasterize_svg");
+    let svg_path = dir.join("icon.tga$mtlkind=ui.svg");
+    fs::write(&svg_path, svg_data).unwrap();
+
+    // Authored size by default
+    let mut output = String::new();
+    let img = rasterize_svg_file(&svg_path, "icon.tga$mtlkind=ui.svg", &mut output).unwrap();
+    assert_eq!(img.dimensions(), (32, 16));
+    assert_eq!(img.get_pixel(4, 8).0, [255, 0, 0, 255]);
+    assert_eq!(img.get_pixel(28, 8)[3], 0);
+
+    // Requested width, keeping the aspect ratio
+    let img = rasterize_svg_file(&svg_path, "icon.tga$mtlkind=ui$svgsize=128.svg", &mut output).unwrap();
+    assert_eq!(img.dimensions(), (128, 64));
+    assert_eq!(img.get_pixel(60, 32).0, [255, 0, 0, 255]);
+    assert_eq!(img.get_pixel(68, 32)[3], 0);
+
+    // Size of the texture being replaced
+    RgbaImage::new(256, 256)
+        .save_with_format(dir.join("icon.tga$mtlkind=ui"), ImageFormat::Png)
+        .unwrap();
+    let img = rasterize_svg_file(&svg_path, "icon.tga$mtlkind=ui.svg", &mut output).unwrap();
+    assert_eq!(img.dimensions(), (256, 256));
+    assert!(output.contains("Matching the size of the texture being replaced"));
+}
+
+#[test]
+fn test_source_channel() {
+    // Opaque image, so alpha textures are read from luminance by default
+    let src_img = RgbaImage::from_pixel(4, 4, image::Rgba([200, 100, 50, 255]));
+    let file_path = Path::new("./test/missing.tga$alpha$nomips.png");
+    let mut output = String::new();
+
+    let bim_bytes = convert_rgba_image(
+        src_img.clone(),
+        file_path,
+        "missing.tga$alpha$nomips.png",
+        "missing".into(),
+        false,
+        &mut output
+    )
+    .unwrap();
+
+    let mip_start = BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE;
+    assert_eq!(bim_bytes[mip_start..], [124_u8; 16]);
+    assert!(output.contains("from the luminance channel"));
+
+    // Explicit channel
+    let bim_bytes = convert_rgba_image(
+        src_img.clone(),
+        file_path,
+        "missing.tga$alpha$nomips$channel=g.png",
+        "missing".into(),
+        false,
+        &mut output
+    )
+    .unwrap();
+    assert_eq!(bim_bytes[mip_start..], [100_u8; 16]);
+
+    // Textures smaller than the end marker are kept whole
+    let bim_bytes = convert_rgba_image(
+        RgbaImage::from_pixel(1, 1, image::Rgba([200, 100, 50, 255])),
+        file_path,
+        "missing.tga$alpha.png",
+        "missing".into(),
+        false,
+        &mut output
+    )
+    .unwrap();
+    assert_eq!(bim_bytes[mip_start..], [124_u8]);
+
+    // BC4 reads red by default, even with transparency, and alpha only when asked to
+    let imgs = || {
+        [
+            RgbaImage::from_pixel(4, 4, image::Rgba([200, 100, 50, 30])),
+            RgbaImage::from_pixel(2, 2, image::Rgba([10, 0, 0, 60]))
+        ]
+    };
+
+    let mut bc4_imgs = imgs();
+    apply_source_channel(
+        bc4_imgs.iter_mut(),
+        "test.tga$bc4.png",
+        TextureFormat::FmtBc4,
+        &mut output
+    )
+    .unwrap();
+    assert_eq!(bc4_imgs[0].get_pixel(0, 0).0, [200, 200, 200, 255]);
+    assert_eq!(bc4_imgs[1].get_pixel(0, 0).0, [10, 10, 10, 255]);
+
+    let mut bc4_imgs = imgs();
+    apply_source_channel(
+        bc4_imgs.iter_mut(),
+        "test.tga$bc4$channel=a.png",
+        TextureFormat::FmtBc4,
+        &mut output
+    )
+    .unwrap();
+    assert_eq!(bc4_imgs[0].get_pixel(0, 0).0, [30, 30, 30, 255]);
+    assert_eq!(bc4_imgs[1].get_pixel(0, 0).0, [60, 60, 60, 255]);
+
+    // Transparent alpha textures keep their alpha
+    let mut alpha_imgs = imgs();
+    apply_source_channel(
+        alpha_imgs.iter_mut(),
+        "test.tga$alpha.png",
+        TextureFormat::FmtAlpha,
+        &mut output
+    )
+    .unwrap();
+    assert_eq!(alpha_imgs[0].get_pixel(0, 0).0, [30; 4]);
+
+    // Other formats are left alone
+    let mut img = src_img.clone();
+    apply_source_channel(
+        iter::once(&mut img),
+        "test.png",
+        TextureFormat::FmtBc1Srgb,
+        &mut output
+    )
+    .unwrap();
+    assert_eq!(img, src_img);
+
+    assert!(apply_source_channel(
+        iter::once(&mut img),
+        "test.tga$alpha$channel=x.png",
+        TextureFormat::FmtAlpha,
+        &mut output
+    )
+    .is_err());
+}
+
+#[test]
+fn test_pack_channels() {
+    let dir = TempDir::new("pack_channels");
+
+    RgbaImage::from_pixel(4, 4, image::Rgba([10, 20, 30, 40]))
+        .save(dir.join("mask.png"))
+        .unwrap();
+    RgbaImage::from_pixel(4, 4, image::Rgba([100, 100, 100, 255]))
+        .save(dir.join("ao.png"))
+        .unwrap();
+
+    let recipe_path = dir.join("packed.tga$bc7.pack");
+    fs::write(
+        &recipe_path,
+        "# Occlusion, inverted mask alpha and a constant\nr = ao.png\ng = mask.png:a invert\nb = 128\n"
+    )
+    .unwrap();
+
+    let mut output = String::new();
+    let img = pack_recipe_channels(&recipe_path, &mut output).unwrap();
+    assert_eq!(img.dimensions(), (4, 4));
+    assert_eq!(img.get_pixel(3, 3).0, [100, 215, 128, 255]);
+    assert!(output.contains("from 2 images"));
+
+    // Sources must match in size
+    RgbaImage::new(8, 8).save(dir.join("big.png")).unwrap();
+    fs::write(&recipe_path, "r = ao.png\ng = big.png:r\n").unwrap();
+    assert!(pack_recipe_channels(&recipe_path, &mut output).is_err());
+
+    let base_dir = Path::new("textures");
+    let recipe = PackRecipe::parse("a = 0 invert\nred = gloss.png:green", base_dir).unwrap();
+    assert_eq!(
+        recipe.sources[0],
+        channels::ChannelSource::Image {
+            path: base_dir.join("gloss.png"),
+            channel: Channel::Green,
+            invert: false
+        }
+    );
+    assert_eq!(recipe.sources[3], channels::ChannelSource::Constant(255));
+
+    assert!(PackRecipe::parse("x = ao.png", base_dir).is_err());
+    assert!(PackRecipe::parse("r ao.png", base_dir).is_err());
+}
+
+#[test]
+fn test_normal_preprocessing() {
+    // Unnormalized vector with a transparent alpha
+    let src_img = RgbaImage::from_pixel(4, 4, image::Rgba([191, 64, 191, 0]));
+    let mut output = String::new();
+
+    // Re-encoded as is, but opaque
+    let mut img = src_img.clone();
+    apply_normal_preprocessing(
+        iter::once(&mut img),
+        "rock_n.tga",
+        "rock_n",
+        TextureFormat::FmtBc5,
+        &mut output
+    );
+    assert_eq!(img.get_pixel(0, 0).0, [191, 64, 191, 255]);
+
+    // Y flipped and renormalized
+    let mut img = src_img.clone();
+    apply_normal_preprocessing(
+        iter::once(&mut img),
+        "rock_n.tga$flipy$renormalize",
+        "rock_n",
+        TextureFormat::FmtBc5,
+        &mut output
+    );
+    assert_eq!(img.get_pixel(0, 0).0, [201, 201, 201, 255]);
+    assert!(output.contains("Flipping") && output.contains("Renormalizing"));
+
+    // Normal maps in formats with alpha keep it
+    let mut img = src_img.clone();
+    apply_normal_preprocessing(
+        iter::once(&mut img),
+        "rock_n.tga$bc7$flipy",
+        "rock_n",
+        TextureFormat::FmtBc7,
+        &mut output
+    );
+    assert_eq!(img.get_pixel(0, 0).0, [191, 191, 191, 0]);
+
+    // Other textures are left alone
+    let mut img = src_img.clone();
+    apply_normal_preprocessing(
+        iter::once(&mut img),
+        "rock.tga$flipy",
+        "rock",
+        TextureFormat::FmtBc1Srgb,
+        &mut output
+    );
+    assert_eq!(img, src_img);
+}
+
+#[test]
+fn test_height_to_normal_map() {
+    // Ramp rising to the right
+    let height_img = RgbaImage::from_fn(8, 8, |x, _| {
+        let value = (x * 32) as u8;
+        image::Rgba([value, value, value, 255])
+    });
+
+    let normal_img = normals::height_to_normal_map(&height_img, 4.0, HeightFilter::Sobel);
+    assert_eq!(normal_img.dimensions(), (8, 8));
+
+    // Slope of 32 / 255 per pixel, scaled by 4, tilts the normal to the left
+    assert_eq!(normal_img.get_pixel(4, 4).0, [70, 128, 241, 255]);
+
+    // Scharr gives the same slope on a linear ramp
+    let scharr_img = normals::height_to_normal_map(&height_img, 4.0, HeightFilter::Scharr);
+    assert_eq!(scharr_img.get_pixel(4, 4), normal_img.get_pixel(4, 4));
+
+    // Flat heightmaps point straight out
+    let flat_img = RgbaImage::from_pixel(4, 4, image::Rgba([100, 100, 100, 255]));
+    let flat_normal_img = normals::height_to_normal_map(&flat_img, 4.0, HeightFilter::Sobel);
+    assert_eq!(flat_normal_img.get_pixel(0, 0).0, [128, 128, 255, 255]);
+
+    // Normal map names
+    assert_eq!(
+        get_height_normal_map_name("rock_h.tga$normals.png", "rock_h"),
+        Some("rock_n".into())
+    );
+    assert_eq!(
+        get_height_normal_map_name("rock.tga$mtlkind=heightmap$normals.png", "rock"),
+        Some("rock_n".into())
+    );
+    assert_eq!(get_height_normal_map_name("rock_h.tga.png", "rock_h"), None);
+    assert_eq!(get_height_normal_map_name("rock.tga$normals.png", "rock"), None);
+
+    // Both textures are written in one run
+    let dir = TempDir::new("height_to_normal_map");
+    let height_path = dir.join("rock_h.tga$normals$strength=4.png");
+    height_img
+        .save_with_format(&height_path, ImageFormat::Png)
+        .unwrap();
+
+    let mut output = String::new();
+    let new_files = convert_file(
+        height_path.to_str().unwrap(),
+        "rock_h.tga$normals$strength=4.png",
+        "rock_h".into(),
+        &mut output
+    )
+    .unwrap();
+    let new_names: Vec<&str> = new_files
+        .iter()
+        .map(|(_, path)| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(new_names, ["rock_n.tga", "rock_h.tga$normals$strength=4"]);
+
+    // The normal map gets a KTX2 copy too, and no bimage when only KTX2 is exported
+    // The heightmap has no extension, so its own output is named around it
+    let height_path = dir.join("pebble_h.tga$normals$strength=2.5");
+    height_img
+        .save_with_format(&height_path, ImageFormat::Tga)
+        .unwrap();
+
+    let mut output = String::new();
+    let new_files = convert_file_with(
+        height_path.to_str().unwrap(),
+        "pebble_h.tga$normals$strength=2.5",
+        "pebble_h".into(),
+        true,
+        Some("only"),
+        &mut output
+    )
+    .unwrap();
+    let new_names: Vec<&str> = new_files
+        .iter()
+        .map(|(_, path)| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(
+        new_names,
+        ["pebble_n.tga.ktx2", "pebble_h.tga$normals$strength=2.5-2.ktx2"]
+    );
+    assert!(output.contains("strength of 2.5"));
+}
+
+#[test]
+fn test_convert_pbr() {
+    let dir = TempDir::new("convert_pbr");
+
+    // Left half metal, right half dielectric
+    RgbaImage::from_pixel(4, 4, image::Rgba([200, 100, 50, 255]))
+        .save(dir.join("rock_basecolor.png"))
+        .unwrap();
+    RgbaImage::from_fn(4, 4, |x, _| match x < 2 {
+        true => image::Rgba([0, 64, 255, 255]),
+        false => image::Rgba([0, 64, 0, 255])
+    })
+    .save(dir.join("rock_orm.png"))
+    .unwrap();
+
+    let recipe_path = dir.join("rock.pbr");
+    fs::write(
+        &recipe_path,
+        "basecolor = rock_basecolor.png\nmetallic = rock_orm.png:b\nroughness = rock_orm.png:g\n"
+    )
+    .unwrap();
+
+    let recipe = PbrRecipe::parse(&fs::read_to_string(&recipe_path).unwrap(), &dir).unwrap();
+    assert_eq!(recipe.image_paths().len(), 2);
+
+    let images: HashMap<&Path, RgbaImage> = recipe
+        .image_paths()
+        .into_iter()
+        .map(|path| (path, load_image(path, &mut String::new()).unwrap()))
+        .collect();
+    let [albedo_img, specular_img, smoothness_img] = recipe.convert(&images).unwrap();
+
+    // Metals reflect their base color and have no diffuse color
+    assert_eq!(albedo_img.get_pixel(0, 0).0, [0, 0, 0, 255]);
+    assert_eq!(specular_img.get_pixel(0, 0).0, [200, 100, 50, 255]);
+
+    // Dielectrics keep their base color and reflect 4%
+    assert_eq!(albedo_img.get_pixel(3, 0).0, [200, 100, 50, 255]);
+    assert_eq!(specular_img.get_pixel(3, 0).0, [56, 56, 56, 255]);
+
+    assert_eq!(smoothness_img.get_pixel(0, 0).0, [191, 191, 191, 255]);
+
+    // Three textures are written, named for their material kind
+    let mut output = String::new();
+    let new_files = convert_pbr_file(&recipe_path, "rock.pbr", "rock", false, None, &mut output).unwrap();
+    let new_names: Vec<&str> = new_files
+        .iter()
+        .map(|(_, path)| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(new_names, ["rock.tga", "rock_s.tga", "rock_g.tga"]);
+
+    // The recipe's tokens apply to every texture, and KTX2 copies can be exported
+    let recipe_path = dir.join("rock$nomips.pbr");
+    fs::copy(dir.join("rock.pbr"), &recipe_path).unwrap();
+
+    let new_files = convert_pbr_file(
+        &recipe_path,
+        "rock$nomips.pbr",
+        "rock",
+        true,
+        Some("only"),
+        &mut output
+    )
+    .unwrap();
+    assert_eq!(new_files.len(), 3);
+
+    for (ktx2_bytes, path) in new_files {
+        assert_eq!(path.extension().unwrap(), "ktx2");
+        assert_eq!(ktx2_bytes[40..44], 1_u32.to_le_bytes());
+    }
+
+    assert!(PbrRecipe::parse("roughness = 128", Path::new("")).is_err());
+    assert!(PbrRecipe::parse("basecolor = a.png\nroughness = 128\nspecular = 0", Path::new("")).is_err());
+}
+
+#[test]
+fn test_dilate_transparent() {
+    // Red left column and half transparent green right column, with transparent black between them
+    let img = RgbaImage::from_fn(8, 4, |x, _| match x {
+        0 => image::Rgba([255, 0, 0, 255]),
+        7 => image::Rgba([0, 255, 0, 128]),
+        _ => image::Rgba([0, 0, 0, 0])
+    });
+
+    let mut pixels = img.into_raw();
+    dilate::dilate_transparent(&mut pixels, 8, 4);
+    let img = RgbaImage::from_raw(8, 4, pixels).unwrap();
+
+    // Colors spread from the nearest visible pixel, and alpha stays the same
+    assert_eq!(img.get_pixel(3, 2).0, [255, 0, 0, 0]);
+    assert_eq!(img.get_pixel(4, 2).0, [0, 255, 0, 0]);
+    assert_eq!(img.get_pixel(7, 2).0, [0, 255, 0, 128]);
+
+    // Fully transparent images are left alone
+    let mut pixels = vec![0_u8; 64];
+    dilate::dilate_transparent(&mut pixels, 4, 4);
+    assert_eq!(pixels, [0_u8; 64]);
+
+    // On by default for decals and particles only
+    assert!(should_dilate(
+        "fx.tga$mtlkind=particle",
+        TextureMaterialKind::TmkParticle
+    ));
+    assert!(!should_dilate(
+        "fx.tga$mtlkind=particle$nodilate",
+        TextureMaterialKind::TmkParticle
+    ));
+    assert!(!should_dilate("rock.tga", TextureMaterialKind::TmkAlbedo));
+    assert!(should_dilate("rock.tga$dilate", TextureMaterialKind::TmkAlbedo));
+
+    // Every slice of particle arrays is dilated too
+    let slices = || {
+        (0..2)
+            .map(|_| {
+                RgbaImage::from_fn(8, 8, |x, _| match x {
+                    0 => image::Rgba([255, 0, 0, 255]),
+                    _ => image::Rgba([0, 0, 0, 0])
+                })
+            })
+            .collect()
+    };
+    let convert_slices = |file_name: &str| {
+        convert_slices_to_bimage(
+            slices(),
+            file_name.into(),
+            "fx".into(),
+            TextureFormat::FmtBc7,
+            false
+        )
+        .unwrap()
+    };
+
+    let dilated_bytes = convert_slices("fx.tga$mtlkind=particle$slice0.png");
+    let plain_bytes = convert_slices("fx.tga$mtlkind=particle$nodilate$slice0.png");
+    let mip_count = BIMHeader::from_bytes(&dilated_bytes).unwrap().mip_count as usize;
+    let data_start = BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * 2 * mip_count;
+    let slice_size: usize = (0..mip_count)
+        .map(|entry| {
+            let entry_start = BIM_HEADER_SIZE + BIM_MIP_MAP_SIZE * entry;
+            BIMMipMap::from_bytes(&dilated_bytes[entry_start..entry_start + BIM_MIP_MAP_SIZE])
+                .decompressed_size as usize
+        })
+        .sum();
+
+    for slice in 0..2 {
+        let slice_start = data_start + slice * slice_size;
+        assert_ne!(
+            dilated_bytes[slice_start..slice_start + 64],
+            plain_bytes[slice_start..slice_start + 64]
+        );
+    }
+}
+
+#[test]
+fn test_premultiplied_mips() {
+    // Half transparent orange, with a custom mip 1 in straight alpha
+    let src_img = RgbaImage::from_pixel(8, 8, image::Rgba([200, 100, 50, 128]));
+    let custom_mips = || HashMap::from([(1, RgbaImage::from_pixel(4, 4, image::Rgba([255, 255, 255, 51])))]);
+
+    // Straight alpha by default
+    let mips = generate_mips_cascade(src_img.clone(), custom_mips(), 4, false);
+    assert_eq!(mips[0][0..4], [200, 100, 50, 128]);
+    assert_eq!(mips[1][0..4], [255, 255, 255, 51]);
+    assert_eq!(mips[2][0..4], [255, 255, 255, 51]);
+
+    // Every stored pixel has its color multiplied by alpha, for both mip generation methods
+    let cascade_mips = generate_mips_cascade(src_img.clone(), custom_mips(), 4, true);
+    let direct_mips = generate_mips_direct(src_img.clone(), custom_mips(), 4, true);
+
+    for mips in [&cascade_mips, &direct_mips] {
+        assert_eq!(mips[0][0..4], [100, 50, 25, 128]);
+        assert_eq!(mips[1][0..4], [51, 51, 51, 51]);
+        assert!(mips[0].chunks_exact(4).all(|pixel| pixel == [100, 50, 25, 128]));
+    }
+
+    assert_eq!(cascade_mips[3][0..4], [51, 51, 51, 51]);
+    assert_eq!(direct_mips[3][0..4], [100, 50, 25, 128]);
+
+    // Fully transparent pixels are black, so they aren't dilated
+    let transparent_img = RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 0]));
+    let mips = generate_mips_cascade(transparent_img, HashMap::new(), 1, true);
+    assert_eq!(mips[0][0..4], [0, 0, 0, 0]);
+
+    assert!(!should_dilate(
+        "fx.tga$mtlkind=particle$premultiplied",
+        TextureMaterialKind::TmkParticle
+    ));
+
+    // Prefiltered cubemaps are premultiplied too
+    let faces = || (0..6).map(|_| src_img.clone()).collect();
+    let bim_bytes = convert_cubemap_to_bimage(
+        faces(),
+        "env.tga$cube$bc7$prefilter$samples=4.png".into(),
+        "env".into(),
+        TextureFormat::FmtBc7,
+        false
+    )
+    .unwrap();
+    let premultiplied_bim_bytes = convert_cubemap_to_bimage(
+        faces(),
+        "env.tga$cube$bc7$prefilter$samples=4$premultiplied.png".into(),
+        "env".into(),
+        TextureFormat::FmtBc7,
+        false
+    )
+    .unwrap();
+    assert_ne!(bim_bytes, premultiplied_bim_bytes);
+}
@@ -1,3 +1,173 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr
+};
+
+// Split the extension off a filename (e.g. 'name.tga$bc7.png' -> 'name.tga$bc7', 'png')
+// Names often end in their tokens instead, so only a dot followed by a word counts, not one in a value like '$strength=2.5'
+pub fn split_extension(file_name: &str) -> (&str, Option<&str>) {
+    match file_name.rsplit_once('.') {
+        Some((file_stem, extension))
+            if extension.starts_with(|c: char| c.is_ascii_alphabetic())
+                && extension.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            (file_stem, Some(extension))
+        },
+        _ => (file_name, None)
+    }
+}
+
+// Get the '$' tokens in the filename
+fn tokens(file_name: &str) -> impl Iterator<Item = &str> {
+    let (file_stem, _) = split_extension(file_name);
+    file_stem.split('$').skip(1)
+}
+
+// Check if the filename has a '$key' token without a value
+pub fn has_token(file_name: &str, key: &str) -> bool {
+    tokens(file_name).any(|token| token == key)
+}
+
+// Get the value of a '$key=value' token in the filename, if present
+pub fn get_token_value<'a>(file_name: &'a str, key: &str) -> Option<&'a str> {
+    tokens(file_name).find_map(|token| token.strip_prefix(key)?.strip_prefix('='))
+}
+
+// Parse the value of a '$key=value' token in the filename, if present
+pub fn parse_token<T: FromStr>(file_name: &str, key: &str) -> Result<Option<T>, String> {
+    match get_token_value(file_name, key) {
+        Some(value) => match value.parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(format!("Invalid value '{}' for '${}'", value, key))
+        },
+        None => Ok(None)
+    }
+}
+
+// Get the index of a '$<prefix>N' sibling file (e.g. 'name$mip1.png'), if it is one
+fn sibling_index(path: &Path, prefix: &str) -> Option<u32> {
+    let (file_stem, _) = split_extension(path.file_name()?.to_str()?);
+    let (_, token) = file_stem.rsplit_once('$')?;
+    token.strip_prefix(prefix)?.parse().ok()
+}
+
+// Get the path of the '$<prefix>N' sibling file for the given texture and index
+fn sibling_path(path: &Path, prefix: &str, index: u32) -> Option<PathBuf> {
+    let (file_stem, extension) = split_extension(path.file_name()?.to_str()?);
+    Some(path.with_file_name(join_extension(
+        format!("{}${}{}", file_stem, prefix, index),
+        extension
+    )))
+}
+
+// Put the extension back on a file stem, if there was one
+fn join_extension(file_stem: String, extension: Option<&str>) -> String {
+    match extension {
+        Some(extension) => format!("{}.{}", file_stem, extension),
+        None => file_stem
+    }
+}
+
+// Get the level of a custom mip file (e.g. 'name$mip1.png'), if it is one
+pub fn custom_mip_level(path: &Path) -> Option<u32> {
+    sibling_index(path, "mip").filter(|level| *level > 0)
+}
+
+// Get the path of the custom mip file for the given texture and level
+pub fn custom_mip_path(path: &Path, level: u32) -> Option<PathBuf> {
+    sibling_path(path, "mip", level)
+}
+
+// Get the face of a cubemap face file (e.g. 'name$face0.png'), if it is one
+pub fn cube_face_index(path: &Path) -> Option<u32> {
+    sibling_index(path, "face").filter(|face| *face < 6)
+}
+
+// Get the path of the given face, from the path of any face of the same cubemap
+pub fn cube_face_path(path: &Path, face: u32) -> Option<PathBuf> {
+    replace_last_token(path, "face", face)
+}
+
+// Get the slice of an array or volume slice file (e.g. 'name$slice0.png'), if it is one
+pub fn slice_index(path: &Path) -> Option<u32> {
+    sibling_index(path, "slice")
+}
+
+// Get the path of the given slice, from the path of any slice of the same texture
+pub fn slice_path(path: &Path, slice: u32) -> Option<PathBuf> {
+    replace_last_token(path, "slice", slice)
+}
+
+// Replace the last '$' token of the file stem with '$<prefix>N' (e.g. 'name$face0.png' -> 'name$face3.png')
+fn replace_last_token(path: &Path, prefix: &str, index: u32) -> Option<PathBuf> {
+    let (file_stem, extension) = split_extension(path.file_name()?.to_str()?);
+    let (base_stem, _) = file_stem.rsplit_once('$')?;
+    Some(path.with_file_name(join_extension(
+        format!("{}${}{}", base_stem, prefix, index),
+        extension
+    )))
+}
+
+// Remove the last '$' token from the file stem (e.g. 'name$face0.png' -> 'name.png')
+pub fn strip_last_token(path: &Path) -> Option<PathBuf> {
+    let (file_stem, extension) = split_extension(path.file_name()?.to_str()?);
+    let (base_stem, _) = file_stem.rsplit_once('$')?;
+    Some(path.with_file_name(join_extension(base_stem.to_owned(), extension)))
+}
+
+// Check if the filename has one of the given extensions, ignoring case
+fn has_extension(file_name: &str, extensions: &[&str]) -> bool {
+    match file_name.rsplit_once('.') {
+        Some((_, extension)) => extensions.iter().any(|ext| extension.eq_ignore_ascii_case(ext)),
+        None => false
+    }
+}
+
+// Check if the filename is a TrueType or OpenType font (e.g. 'name.tga$alpha.ttf')
+pub fn is_font_file(file_name: &str) -> bool {
+    has_extension(file_name, &["ttf", "otf"])
+}
+
+// Check if the filename is an SVG document (e.g. 'name.tga$mtlkind=ui.svg')
+pub fn is_svg_file(file_name: &str) -> bool {
+    has_extension(file_name, &["svg"])
+}
+
+// Check if the filename is a channel packing recipe (e.g. 'name.tga$bc7.pack')
+pub fn is_pack_file(file_name: &str) -> bool {
+    has_extension(file_name, &["pack"])
+}
+
+// Check if the filename is a metal/roughness conversion recipe (e.g. 'rock.pbr')
+pub fn is_pbr_file(file_name: &str) -> bool {
+    has_extension(file_name, &["pbr"])
+}
+
+// Convert an 8-bit sRGB value to linear
+pub fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+
+    match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// Convert a linear value to 8-bit sRGB
+pub fn linear_to_srgb(value: f32) -> u8 {
+    let value = match value <= 0.0031308 {
+        true => value * 12.92,
+        false => 1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+
+    to_unorm8(value)
+}
+
+// Convert a value from 0 to 1 to 8 bits
+pub fn to_unorm8(value: f32) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
 // Simulates the 'pause' system command on Windows
 #[cfg(target_os = "windows")]
 pub fn press_any_key() {